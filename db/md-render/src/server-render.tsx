@@ -13,7 +13,7 @@ import { RenderingContext } from "./cohost/lib/markdown/shared-types";
 import { chooseAgeRuleset } from "./cohost/lib/markdown/sanitize";
 import { WirePostViewModel } from "./cohost/shared/types/wire-models";
 import { Element, Node, Parent, Root } from "hast";
-import { Image, Node as MdastNode, Parent as MdastParent, Root as MdastRoot } from "mdast";
+import { Image, Link, Node as MdastNode, Parent as MdastParent, Root as MdastRoot } from "mdast";
 import { unified } from "unified";
 import { Raw } from "mdast-util-to-hast";
 import rehypeRaw from "rehype-raw";
@@ -30,6 +30,14 @@ interface PostRenderRequest {
     publishedAt: string;
     hasCohostPlus: boolean;
     resources: string[];
+    links: Record<string, string>;
+    waybackLinks: boolean;
+}
+
+interface LinkRewrites {
+    /** local URLs for archived things, keyed by cohost.org URL without the hash */
+    archived: Record<string, string>;
+    wayback: boolean;
 }
 
 interface PostResult {
@@ -68,7 +76,7 @@ function makeResourceURL(urlStr: string): string {
     return `/r/${proto}/${url.host}${url.pathname}${search.size ? `?${search}` : ''}`;
 }
 
-function rewriteMdastPlugin(resources: string[]) {
+function rewriteMdastPlugin(resources: string[], archivedLinks: Record<string, string> = {}) {
     const rewrite = (node: MdastNode) => {
         if (node.type === "image") {
             const image = node as Image;
@@ -77,6 +85,15 @@ function rewriteMdastPlugin(resources: string[]) {
             }
         }
 
+        if (node.type === "link") {
+            const link = node as Link;
+            const url = parseCohostURL(link.url);
+            const local = url && archivedLinks[`https://cohost.org${url.pathname}${url.search}`];
+            if (local) {
+                return { ...link, url: local + url.hash, children: link.children.map(rewrite) };
+            }
+        }
+
         if ("children" in node) {
             const parent = node as MdastParent;
             return { ...parent, children: parent.children.map(rewrite) }
@@ -88,7 +105,7 @@ function rewriteMdastPlugin(resources: string[]) {
     return () => (tree: MdastRoot) => rewrite(tree) as MdastRoot;
 }
 
-function rewriteMarkdownString(markdown: string, resources: string[], date: Date): string {
+function rewriteMarkdownString(markdown: string, resources: string[], date: Date, archivedLinks: Record<string, string> = {}): string {
     const ruleset = chooseAgeRuleset(date);
 
     let processor = unified().use(remarkParse);
@@ -99,14 +116,14 @@ function rewriteMarkdownString(markdown: string, resources: string[], date: Date
 
     return processor
         .use(remarkGfm, { singleTilde: false })
-        .use(rewriteMdastPlugin(resources))
+        .use(rewriteMdastPlugin(resources, archivedLinks))
         .use(remarkStringify)
         .processSync(markdown)
         .toString();
 }
 
-function rewriteAsk(ask: AskViewBlock, resources: string[]): AskViewBlock {
-    const content = rewriteMarkdownString(ask.ask.content, resources, new Date(ask.ask.sentAt));
+function rewriteAsk(ask: AskViewBlock, resources: string[], archivedLinks: Record<string, string>): AskViewBlock {
+    const content = rewriteMarkdownString(ask.ask.content, resources, new Date(ask.ask.sentAt), archivedLinks);
 
     if (!ask.ask.anon) {
         let askingProject = { ...ask.ask.askingProject };
@@ -161,7 +178,64 @@ function rewriteAttachmentRow(attachmentRow: AttachmentRowViewBlock, resources:
     };
 }
 
-function rewriteHast<N extends Node>(node: N, resources: string[]): N {
+function parseCohostURL(href: string): URL | null {
+    // links within the post, like footnotes
+    if (href.startsWith("#")) return null;
+
+    let url: URL;
+    try {
+        url = new URL(href, "https://cohost.org/");
+    } catch {
+        return null;
+    }
+    if (url.hostname !== "cohost.org" && url.hostname !== "www.cohost.org") {
+        return null;
+    }
+    return url;
+}
+
+function rewriteCohostLink(properties: Element["properties"], links: LinkRewrites): Element["properties"] {
+    const url = typeof properties.href === "string" ? parseCohostURL(properties.href) : null;
+    if (!url) return properties;
+
+    const local = links.archived[`https://cohost.org${url.pathname}${url.search}`];
+    if (local) {
+        return { ...properties, href: local + url.hash };
+    }
+    return {
+        ...properties,
+        href: url.href,
+        dataNotArchived: true,
+        title: properties.title ?? "not archived",
+    };
+}
+
+function waybackLink(href: string): Element {
+    return {
+        type: "element",
+        tagName: "a",
+        properties: {
+            href: `https://web.archive.org/web/${href}`,
+            className: ["wayback-link"],
+            target: "_blank",
+            rel: ["noopener", "noreferrer"],
+        },
+        children: [{ type: "text", value: "(wayback)" }],
+    };
+}
+
+/** Adds a Wayback Machine link after links to cohost.org that aren't archived. */
+function withWaybackLink(node: Node, links: LinkRewrites | null): Node[] {
+    if (links?.wayback && node.type === "element") {
+        const element = node as Element;
+        if (element.properties.dataNotArchived && typeof element.properties.href === "string") {
+            return [node, { type: "text", value: " " } as Node, waybackLink(element.properties.href)];
+        }
+    }
+    return [node];
+}
+
+function rewriteHast<N extends Node>(node: N, resources: string[], links: LinkRewrites | null = null): N {
     if (node.type === "element") {
         const element = node as Node as Element;
 
@@ -217,7 +291,9 @@ function rewriteHast<N extends Node>(node: N, resources: string[]): N {
             }
         }
 
-        if (properties.dataTestid == "mention" && typeof properties.href === "string") {
+        if (links) {
+            properties = rewriteCohostLink(properties, links);
+        } else if (properties.dataTestid == "mention" && typeof properties.href === "string") {
             properties = {
                 ...properties,
                 href: new URL(properties.href, "https://cohost.org").pathname,
@@ -227,7 +303,7 @@ function rewriteHast<N extends Node>(node: N, resources: string[]): N {
         return {
             ...element,
             properties,
-            children: element.children.map((child) => rewriteHast(child, resources)),
+            children: element.children.flatMap((child) => withWaybackLink(rewriteHast(child, resources, links), links)),
         } as Node as N;
     }
 
@@ -235,7 +311,7 @@ function rewriteHast<N extends Node>(node: N, resources: string[]): N {
         const parent = node as Parent;
         return {
             ...parent,
-            children: parent.children.map((child) => rewriteHast(child, resources)),
+            children: parent.children.flatMap((child) => withWaybackLink(rewriteHast(child, resources, links), links)),
         } as Node as N;
     }
 
@@ -246,7 +322,7 @@ export async function renderPost(args: PostRenderRequest): Promise<PostResult> {
     const blocks = [];
     for (const block of args.blocks) {
         if (block.type === "ask") {
-            blocks.push(rewriteAsk(block, args.resources));
+            blocks.push(rewriteAsk(block, args.resources, args.links));
         } else if (block.type === "attachment") {
             blocks.push(rewriteAttachment(block, args.resources));
         } else if (block.type === "attachment-row") {
@@ -265,7 +341,7 @@ export async function renderPost(args: PostRenderRequest): Promise<PostResult> {
 
     for (const span of postAst.spans) {
         let ast: Root = JSON.parse(span.ast);
-        ast = rewriteHast(ast, args.resources);
+        ast = rewriteHast(ast, args.resources, { archived: args.links, wayback: args.waybackLinks });
         span.ast = JSON.stringify(ast);
     }
 
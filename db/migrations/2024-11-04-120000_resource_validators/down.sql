@@ -0,0 +1,2 @@
+drop table resource_versions;
+drop table resource_validators;
@@ -0,0 +1,18 @@
+create table resource_validators
+(
+    url           varchar not null primary key,
+    etag          varchar,
+    last_modified varchar,
+    size          bigint  not null,
+    sha256        varchar not null,
+    checked_at    varchar not null
+);
+
+create table resource_versions
+(
+    url         varchar not null,
+    file_path   varchar not null,
+    sha256      varchar,
+    replaced_at varchar not null,
+    primary key (url, file_path) on conflict ignore
+);
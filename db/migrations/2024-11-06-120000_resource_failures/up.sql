@@ -0,0 +1,11 @@
+create table resource_failures
+(
+    url             varchar not null primary key,
+    error_class     varchar not null,
+    status_code     integer,
+    message         varchar not null,
+    attempts        integer not null,
+    first_failed_at varchar not null,
+    last_failed_at  varchar not null,
+    skip            boolean not null
+);
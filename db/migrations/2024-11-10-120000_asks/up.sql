@@ -0,0 +1,14 @@
+create table asks
+(
+    id                varchar not null primary key,
+    post_id           integer not null,
+    asking_project_id integer,
+    asking_handle     varchar collate nocase,
+    anon              boolean not null,
+    logged_in         boolean not null,
+    sent_at           varchar not null,
+    foreign key (post_id) references posts (id) on delete cascade
+);
+
+create index asks_post_id on asks (post_id);
+create index asks_asking_project_id on asks (asking_project_id);
@@ -0,0 +1,2 @@
+drop table post_links;
+drop table mentions;
@@ -0,0 +1,21 @@
+create table mentions
+(
+    post_id integer not null,
+    handle  varchar collate nocase not null,
+    primary key (post_id, handle),
+    foreign key (post_id) references posts (id) on delete cascade
+);
+
+create index mentions_handle on mentions (handle);
+
+create table post_links
+(
+    post_id        integer not null,
+    linked_post_id integer not null,
+    linked_handle  varchar collate nocase not null,
+    primary key (post_id, linked_post_id),
+    foreign key (post_id) references posts (id) on delete cascade
+);
+
+create index post_links_linked_post_id on post_links (linked_post_id);
+create index post_links_linked_handle on post_links (linked_handle);
@@ -0,0 +1,207 @@
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use anyhow::{anyhow, bail, Context};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use sha1::Sha1;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SESSION_COOKIE_NAME: &str = "connect.sid";
+const COHOST_HOSTS: [&str; 2] = ["cohost.org", ".cohost.org"];
+
+mod firefox {
+    diesel::table! {
+        moz_cookies (id) {
+            id -> Integer,
+            name -> Text,
+            value -> Text,
+            host -> Text,
+            expiry -> BigInt,
+        }
+    }
+}
+
+mod chromium {
+    diesel::table! {
+        cookies (creation_utc) {
+            creation_utc -> BigInt,
+            host_key -> Text,
+            name -> Text,
+            value -> Text,
+            encrypted_value -> Binary,
+            expires_utc -> BigInt,
+        }
+    }
+
+    diesel::table! {
+        meta (key) {
+            key -> Text,
+            value -> Text,
+        }
+    }
+}
+
+enum CookieDb {
+    Firefox(PathBuf),
+    Chromium(PathBuf),
+}
+
+/// Finds the cookie database in a profile directory (or a path to the database itself).
+fn find_cookie_db(profile: &Path) -> anyhow::Result<CookieDb> {
+    if profile.is_file() {
+        return match profile.file_name().and_then(|name| name.to_str()) {
+            Some("cookies.sqlite") => Ok(CookieDb::Firefox(profile.into())),
+            Some("Cookies") => Ok(CookieDb::Chromium(profile.into())),
+            _ => bail!(
+                "{} is not a Firefox (cookies.sqlite) or Chromium (Cookies) cookie file",
+                profile.display()
+            ),
+        };
+    }
+
+    let firefox = profile.join("cookies.sqlite");
+    if firefox.is_file() {
+        return Ok(CookieDb::Firefox(firefox));
+    }
+
+    for path in [
+        "Network/Cookies",
+        "Cookies",
+        "Default/Network/Cookies",
+        "Default/Cookies",
+    ] {
+        let chromium = profile.join(path);
+        if chromium.is_file() {
+            return Ok(CookieDb::Chromium(chromium));
+        }
+    }
+
+    bail!("could not find a cookie database in {}", profile.display())
+}
+
+/// Opens a copy of the database, since browsers keep theirs locked while running.
+fn open_copy(path: &Path, temp_dir: &Path) -> anyhow::Result<SqliteConnection> {
+    let copy_path = temp_dir.join("cookies.sqlite");
+    fs::copy(path, &copy_path).with_context(|| format!("copying {}", path.display()))?;
+
+    // uncommitted changes might still be in the WAL file
+    let mut wal_path = path.as_os_str().to_os_string();
+    wal_path.push("-wal");
+    let wal_path = PathBuf::from(wal_path);
+    if wal_path.is_file() {
+        let mut copy_wal_path = copy_path.as_os_str().to_os_string();
+        copy_wal_path.push("-wal");
+        fs::copy(&wal_path, copy_wal_path)
+            .with_context(|| format!("copying {}", wal_path.display()))?;
+    }
+
+    let copy_path = copy_path
+        .to_str()
+        .ok_or(anyhow!("temporary file path is not valid UTF-8"))?;
+    SqliteConnection::establish(copy_path).context("opening cookie database")
+}
+
+fn read_firefox(db: &mut SqliteConnection) -> anyhow::Result<Option<String>> {
+    use firefox::moz_cookies::dsl::*;
+
+    let cookie = moz_cookies
+        .filter(host.eq_any(COHOST_HOSTS))
+        .filter(name.eq(SESSION_COOKIE_NAME))
+        .order_by(expiry.desc())
+        .select(value)
+        .first::<String>(db)
+        .optional()
+        .context("reading cookies")?;
+
+    Ok(cookie)
+}
+
+fn read_chromium(db: &mut SqliteConnection) -> anyhow::Result<Option<String>> {
+    use chromium::cookies::dsl::*;
+
+    let cookie = cookies
+        .filter(host_key.eq_any(COHOST_HOSTS))
+        .filter(name.eq(SESSION_COOKIE_NAME))
+        .order_by(expires_utc.desc())
+        .select((host_key, value, encrypted_value))
+        .first::<(String, String, Vec<u8>)>(db)
+        .optional()
+        .context("reading cookies")?;
+
+    let Some((cookie_host, cookie_value, cookie_encrypted_value)) = cookie else {
+        return Ok(None);
+    };
+
+    if !cookie_value.is_empty() {
+        return Ok(Some(cookie_value));
+    }
+
+    let db_version = {
+        use chromium::meta::dsl::*;
+        meta.filter(key.eq("version"))
+            .select(value)
+            .first::<String>(db)
+            .optional()
+            .context("reading cookie database version")?
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or_default()
+    };
+
+    let mut decrypted = decrypt_chromium_value(&cookie_encrypted_value)?;
+
+    // newer versions prefix the value with a hash of the host
+    if db_version >= 24 {
+        if decrypted.len() < 32 {
+            bail!("decrypted cookie for {cookie_host} is too short");
+        }
+        decrypted.drain(..32);
+    }
+
+    let decrypted = String::from_utf8(decrypted).context("decrypted cookie is not UTF-8")?;
+    Ok(Some(decrypted))
+}
+
+/// Decrypts a cookie value encrypted with the fixed key Chromium uses on Linux when there is no
+/// system keyring.
+fn decrypt_chromium_value(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+    let Some(data) = data.strip_prefix(b"v10") else {
+        if data.starts_with(b"v11") {
+            bail!("this cookie is encrypted with a key from the system keyring, which is not supported. Try logging in with the wizard instead");
+        }
+        bail!("this cookie is encrypted in an unsupported format (this only works with Chromium on Linux)");
+    };
+
+    let mut key = [0; 16];
+    pbkdf2::pbkdf2::<pbkdf2::hmac::Hmac<Sha1>>(b"peanuts", b"saltysalt", 1, &mut key)
+        .context("deriving key")?;
+    let iv = [b' '; 16];
+
+    let mut buf = data.to_vec();
+    let len = Aes128CbcDec::new(&key.into(), &iv.into())
+        .decrypt_padded_mut::<Pkcs7>(&mut buf)
+        .map_err(|_| anyhow!("could not decrypt cookie"))?
+        .len();
+    buf.truncate(len);
+
+    Ok(buf)
+}
+
+/// Reads the cohost.org session cookie from a Firefox or Chromium profile,
+/// and returns it in the form of a cookie header.
+pub fn read_session_cookie(profile: &Path) -> anyhow::Result<String> {
+    let temp_dir = tempfile::tempdir().context("creating temporary directory")?;
+
+    let cookie = match find_cookie_db(profile)? {
+        CookieDb::Firefox(path) => read_firefox(&mut open_copy(&path, temp_dir.path())?)?,
+        CookieDb::Chromium(path) => read_chromium(&mut open_copy(&path, temp_dir.path())?)?,
+    };
+
+    let Some(cookie) = cookie else {
+        bail!("there is no cohost.org session in this browser profile. Are you logged in?");
+    };
+
+    Ok(format!("{SESSION_COOKIE_NAME}={cookie}"))
+}
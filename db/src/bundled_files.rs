@@ -11,6 +11,7 @@ macro_rules! cdl_static {
 cdl_static! {
     CDL_STATIC;
     "base.css": "static/base.css",
+    "blocked.svg": "static/blocked.svg",
     "tailwind-prose.css": "static/tailwind-prose.css",
     "client.js": "md-render/dist/client.js",
 }
@@ -34,16 +35,34 @@ macro_rules! templates {
 
 templates! {
     TEMPLATES;
+    "archive.html",
+    "archive_day.html",
+    "archive_month.html",
+    "asks_feed.html",
+    "backlinks_feed.html",
     "base.html",
     "comments.html",
     "dashboard.html",
+    "epub_chapter.html",
+    "epub_cw.html",
+    "epub_macros.html",
+    "epub_nav.html",
+    "epub_package.xml",
     "error.html",
+    "failures.html",
     "index.html",
     "liked_feed.html",
     "pagination_eggs.html",
     "post.html",
+    "post_shares.html",
+    "project_comments.html",
+    "project_directory.html",
     "project_profile.html",
     "project_sidebar.html",
+    "share_tree.html",
     "single_post.html",
+    "tag_cloud.html",
+    "tag_detail.html",
     "tag_feed.html",
+    "tag_index.html",
 }
@@ -1,38 +1,58 @@
 use crate::data::Database;
-use crate::dl::CurrentStateV1;
-use anyhow::{anyhow, Context};
+use crate::post::{LimitedVisibilityReason, PostFromCohost};
+use crate::post_filter::PostFilter;
+use crate::resource_rules::{ResourceRule, ResourceRules};
+use crate::sniff::{sniff_content_type, SNIFF_LENGTH};
+use crate::trpc::LoginLoggedIn;
+use anyhow::{anyhow, bail, Context};
 use diesel::SqliteConnection;
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use reqwest::header::{
+    ACCEPT_RANGES, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED,
+    RANGE,
+};
 use reqwest::{Client, IntoUrl, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
 use std::time::Duration;
 use tempfile::NamedTempFile;
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 use tokio::time::sleep;
 
 pub const USER_AGENT: &str = "cohost-dl/2.0";
 const MAX_FILE_NAME_LENGTH_UTF8: usize = 250;
 
 pub struct CohostContext {
-    cookie: String,
+    cookie: RwLock<String>,
+    /// Project ID of the active page, if we were logged in at some point.
+    /// Also locked while waiting for the user to log in again.
+    session: Mutex<Option<u64>>,
+    /// Progress bars, which are hidden while asking the user to log in again
+    pub progress: MultiProgress,
+    /// One lock per resource URL that is being downloaded, because downloads of the same URL
+    /// share a partial file
+    downloads: std::sync::Mutex<HashMap<String, Weak<Mutex<()>>>>,
     client: Client,
     pub root_dir: PathBuf,
     temp_dir: PathBuf,
-    pub do_not_fetch_domains: HashSet<String>,
+    pub resource_rules: ResourceRules,
+    pub post_filter: PostFilter,
     pub(crate) db: Database,
 }
 
-struct ResourceUrlProps {
+struct ResourceUrlProps<'a> {
     fetch: Url,
     file_path: PathBuf,
     can_fail: bool,
     skip_file_ext_check: bool,
+    rule: Option<&'a ResourceRule>,
 }
 
 #[derive(Debug, Error)]
@@ -45,6 +65,12 @@ pub enum GetError {
     OtherStatus(Url, StatusCode, String),
     #[error("GET {0}: {1}")]
     Req(Url, reqwest::Error),
+    #[error("TRPC error {code} / {data:?}: {message}")]
+    Trpc {
+        code: i64,
+        data: serde_json::Value,
+        message: String,
+    },
     #[error("{0:?}")]
     Other(anyhow::Error),
 }
@@ -57,6 +83,58 @@ pub enum LoadResError {
     Unknown(anyhow::Error),
 }
 
+/// Errors that might mean that the login session expired.
+pub(crate) trait AuthError {
+    fn is_auth_error(&self) -> bool;
+}
+
+impl AuthError for GetError {
+    fn is_auth_error(&self) -> bool {
+        match self {
+            GetError::OtherStatus(_, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN, _) => true,
+            GetError::Trpc { data, .. } => {
+                data.get("code").and_then(|code| code.as_str()) == Some("UNAUTHORIZED")
+            }
+            _ => false,
+        }
+    }
+}
+
+impl AuthError for anyhow::Error {
+    fn is_auth_error(&self) -> bool {
+        self.chain().any(|e| {
+            e.downcast_ref::<GetError>()
+                .is_some_and(GetError::is_auth_error)
+        })
+    }
+}
+
+#[test]
+fn test_is_auth_error() {
+    let url = Url::parse("https://cohost.org/api/v1/trpc/posts.singlePost").unwrap();
+    let trpc_error = |code: &str| GetError::Trpc {
+        code: -32001,
+        data: serde_json::json!({ "code": code, "httpStatus": 401 }),
+        message: "UNAUTHORIZED".into(),
+    };
+
+    assert!(trpc_error("UNAUTHORIZED").is_auth_error());
+    assert!(!trpc_error("NOT_FOUND").is_auth_error());
+    assert!(
+        GetError::OtherStatus(url.clone(), StatusCode::FORBIDDEN, String::new()).is_auth_error()
+    );
+    assert!(
+        !GetError::OtherStatus(url.clone(), StatusCode::BAD_GATEWAY, String::new()).is_auth_error()
+    );
+    // the message doesn't matter
+    assert!(!GetError::NotFound(url, "UNAUTHORIZED".into()).is_auth_error());
+    assert!(!GetError::Other(anyhow!("UNAUTHORIZED")).is_auth_error());
+
+    let wrapped = anyhow::Error::from(trpc_error("UNAUTHORIZED")).context("loading posts");
+    assert!(wrapped.is_auth_error());
+    assert!(!anyhow!("UNAUTHORIZED").is_auth_error());
+}
+
 impl GetError {
     pub fn is_recoverable(&self) -> bool {
         match self {
@@ -73,6 +151,31 @@ impl GetError {
             _ => true,
         }
     }
+
+    /// Name of the error variant, as stored in the failure list.
+    pub fn class(&self) -> &'static str {
+        match self {
+            GetError::Url(..) => "Url",
+            GetError::NotFound(..) => "NotFound",
+            GetError::OtherStatus(..) => "OtherStatus",
+            GetError::Req(..) => "Req",
+            GetError::Trpc { .. } => "Trpc",
+            GetError::Other(..) => "Other",
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            GetError::NotFound(..) => Some(StatusCode::NOT_FOUND),
+            GetError::OtherStatus(_, status, _) => Some(*status),
+            GetError::Url(e) | GetError::Req(_, e) => e.status(),
+            GetError::Trpc { data, .. } => data
+                .get("httpStatus")
+                .and_then(|status| status.as_u64())
+                .and_then(|status| StatusCode::from_u16(status as u16).ok()),
+            GetError::Other(..) => None,
+        }
+    }
 }
 
 impl LoadResError {
@@ -98,6 +201,62 @@ impl From<std::io::Error> for LoadResError {
 
 pub const MAX_RETRIES: usize = 10;
 
+/// Returns true if a resource that failed to load `attempts` times should not be tried again.
+/// That's the case if retrying won't help, or if it's allowed to fail and has failed too often.
+fn should_skip_failed_resource(error: &GetError, can_fail: bool, attempts: usize) -> bool {
+    !error.is_recoverable() || (can_fail && attempts >= MAX_RETRIES)
+}
+
+#[test]
+fn test_should_skip_failed_resource() {
+    let url = Url::parse("https://example.com/a.png").unwrap();
+    // stands in for a connection error, since those can't be made up
+    let req_error = Client::new().get("http://").build().unwrap_err();
+    let connection_error = GetError::Req(url.clone(), req_error);
+    let server_error =
+        GetError::OtherStatus(url.clone(), StatusCode::SERVICE_UNAVAILABLE, String::new());
+    let not_found = GetError::NotFound(url, String::new());
+
+    for error in [&connection_error, &server_error] {
+        assert!(!should_skip_failed_resource(error, false, 1));
+        assert!(!should_skip_failed_resource(error, false, MAX_RETRIES));
+        assert!(!should_skip_failed_resource(error, true, 1));
+        assert!(!should_skip_failed_resource(error, true, MAX_RETRIES - 1));
+        assert!(should_skip_failed_resource(error, true, MAX_RETRIES));
+    }
+    assert!(should_skip_failed_resource(&not_found, false, 1));
+    assert!(should_skip_failed_resource(&not_found, true, 1));
+}
+
+#[derive(Deserialize)]
+enum TrpcResult<T> {
+    #[serde(rename = "result")]
+    Result { data: T },
+    #[serde(rename = "error")]
+    Error {
+        code: i64,
+        data: serde_json::Value,
+        message: String,
+    },
+}
+
+impl<T> TrpcResult<T> {
+    fn into_result(self) -> Result<T, GetError> {
+        match self {
+            TrpcResult::Result { data } => Ok(data),
+            TrpcResult::Error {
+                code,
+                data,
+                message,
+            } => Err(GetError::Trpc {
+                code,
+                data,
+                message,
+            }),
+        }
+    }
+}
+
 impl Deref for CohostContext {
     type Target = Database;
 
@@ -132,11 +291,15 @@ impl CohostContext {
             .expect("could not canonicalize temp directory");
 
         CohostContext {
-            cookie,
+            cookie: RwLock::new(cookie),
+            session: Mutex::new(None),
+            progress: MultiProgress::new(),
+            downloads: Default::default(),
             client,
             root_dir,
             temp_dir,
-            do_not_fetch_domains: Default::default(),
+            resource_rules: Default::default(),
+            post_filter: Default::default(),
             db: Database::new(db),
         }
     }
@@ -161,7 +324,8 @@ impl CohostContext {
             let mut req = self.client.get(url.clone());
 
             if url.domain() == Some("cohost.org") {
-                req = req.header("cookie", &self.cookie);
+                let cookie = self.cookie.read().unwrap().clone();
+                req = req.header("cookie", cookie);
             }
 
             let res = req.send().await.map_err(|e| GetError::Req(url.clone(), e));
@@ -202,6 +366,96 @@ impl CohostContext {
         }
     }
 
+    /// Loads login.loggedIn without checking for an expired session.
+    pub(crate) async fn fetch_login_logged_in(&self) -> Result<LoginLoggedIn, GetError> {
+        self.trpc_query::<(), _>("login.loggedIn", None).await
+    }
+
+    /// Remembers that we are logged in, so that we notice when the session expires.
+    pub(crate) async fn set_logged_in(&self, login: &LoginLoggedIn) {
+        if login.logged_in {
+            *self.session.lock().await = Some(login.project_id);
+        }
+    }
+
+    /// If we were logged in before but aren't anymore, this will wait until the user logs in again.
+    ///
+    /// Returns true if the session had expired, in which case whatever was just loaded should be
+    /// loaded again, since it was probably loaded while logged out.
+    pub async fn recover_if_logged_out(&self) -> Result<bool, GetError> {
+        // this will also make any other tasks wait while we're recovering
+        let mut session = self.session.lock().await;
+        let Some(project_id) = *session else {
+            // never logged in; nothing to recover
+            return Ok(false);
+        };
+
+        let login = self.fetch_login_logged_in().await?;
+        if login.logged_in {
+            return Ok(false);
+        }
+
+        error!("\x1b[31;1mlooks like your login session expired\x1b[m");
+
+        loop {
+            let old_cookie = self.cookie.read().unwrap().clone();
+
+            // the prompt reads from the terminal, so keep progress bars out of the way and don't
+            // block the other tasks' threads
+            let _ = self.progress.clear();
+            self.progress.set_draw_target(ProgressDrawTarget::hidden());
+            let runtime = tokio::runtime::Handle::current();
+            let cookie = tokio::task::spawn_blocking(move || {
+                runtime.block_on(crate::interactive_session_expired(&old_cookie))
+            })
+            .await;
+            self.progress.set_draw_target(ProgressDrawTarget::stderr());
+
+            let cookie = cookie
+                .map_err(|e| GetError::Other(e.into()))?
+                .map_err(GetError::Other)?;
+            *self.cookie.write().unwrap() = cookie;
+
+            let login = self.fetch_login_logged_in().await?;
+            if !login.logged_in {
+                error!("that session is not logged in either");
+                continue;
+            }
+
+            if login.project_id != project_id {
+                warn!("the active page changed after logging in again");
+            }
+            *session = Some(login.project_id);
+
+            info!("logged in again. resuming");
+            return Ok(true);
+        }
+    }
+
+    /// Checks if a request might have failed or returned incomplete posts because the login
+    /// session expired. If so, waits for the session to be restored and returns true, in which
+    /// case the request should be made again.
+    pub(crate) async fn retry_if_logged_out<T, E: AuthError>(
+        &self,
+        result: &Result<T, E>,
+        posts: impl FnOnce(&T) -> &[PostFromCohost],
+    ) -> Result<bool, GetError> {
+        let suspicious = match result {
+            Ok(data) => posts(data).iter().any(|post| {
+                std::iter::once(post).chain(&post.share_tree).any(|post| {
+                    post.limited_visibility_reason == LimitedVisibilityReason::LogInFirst
+                })
+            }),
+            Err(e) => e.is_auth_error(),
+        };
+
+        if suspicious {
+            self.recover_if_logged_out().await
+        } else {
+            Ok(false)
+        }
+    }
+
     pub async fn get_json<T>(&self, url: impl IntoUrl) -> Result<T, GetError>
     where
         T: for<'a> Deserialize<'a> + 'static,
@@ -264,28 +518,42 @@ impl CohostContext {
             );
         }
 
-        #[derive(Deserialize)]
-        enum TrpcResult<T> {
-            #[serde(rename = "result")]
-            Result { data: T },
-            #[serde(rename = "error")]
-            Error {
-                code: i64,
-                data: serde_json::Value,
-                message: String,
-            },
-        }
+        self.get_json::<TrpcResult<T>>(url).await?.into_result()
+    }
+
+    pub async fn trpc_mutation<A, T>(&self, mutation: &str, input: A) -> Result<T, GetError>
+    where
+        A: Serialize,
+        T: for<'a> Deserialize<'a> + 'static,
+    {
+        let url = Url::parse(&format!("https://cohost.org/api/v1/trpc/{mutation}")).unwrap();
+        trace!("POST {url}");
+
+        let body = serde_json::to_string(&input).map_err(|e| GetError::Other(e.into()))?;
+        let cookie = self.cookie.read().unwrap().clone();
+
+        let res = self
+            .client
+            .post(url.clone())
+            .header("cookie", cookie)
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| GetError::Req(url.clone(), e))?;
+
+        let status = res.status();
+        let text = res
+            .text()
+            .await
+            .map_err(|e| GetError::Req(url.clone(), e))?;
 
-        let result = self.get_json::<TrpcResult<T>>(url).await?;
+        // tRPC errors also have an error status, but those are more useful
+        let result = serde_json::from_str::<TrpcResult<T>>(&text);
         match result {
-            TrpcResult::Result { data } => Ok(data),
-            TrpcResult::Error {
-                code,
-                data,
-                message,
-            } => Err(GetError::Other(anyhow!(
-                "TRPC error {code} / {data:?}: {message}"
-            ))),
+            Ok(result) => result.into_result(),
+            Err(_) if !status.is_success() => Err(GetError::OtherStatus(url, status, text)),
+            Err(e) => Err(GetError::Other(e.into())),
         }
     }
 
@@ -300,6 +568,41 @@ impl CohostContext {
             .await
             .map_err(|e| GetError::Req(url.clone(), e))?;
 
+        Self::file_response(url, res).await
+    }
+
+    /// Like [Self::get_file], but returns None if the server says that the file hasn't changed.
+    pub async fn get_file_if_changed(
+        &self,
+        url: &Url,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<Option<reqwest::Response>, GetError> {
+        trace!("GET {url} (conditional)");
+
+        let mut req = self.client.get(url.clone());
+        if let Some(etag) = etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let res = req
+            .send()
+            .await
+            .map_err(|e| GetError::Req(url.clone(), e))?;
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        Self::file_response(url.clone(), res).await.map(Some)
+    }
+
+    async fn file_response(
+        url: Url,
+        res: reqwest::Response,
+    ) -> Result<reqwest::Response, GetError> {
         let status = res.status();
         if status.is_success() {
             Ok(res)
@@ -326,7 +629,7 @@ impl CohostContext {
         }
     }
 
-    fn props_for_resource_url(&self, url: &Url) -> anyhow::Result<Option<ResourceUrlProps>> {
+    fn props_for_resource_url(&self, url: &Url) -> anyhow::Result<Option<ResourceUrlProps<'_>>> {
         if url.domain() == Some("staging.cohostcdn.org")
             && url
                 .path_segments()
@@ -345,6 +648,7 @@ impl CohostContext {
                 file_path,
                 can_fail: false,
                 skip_file_ext_check: true,
+                rule: None,
             }))
         } else if url.domain() == Some("cohost.org") {
             let mut file_path = self.root_dir.clone();
@@ -360,12 +664,16 @@ impl CohostContext {
                 file_path,
                 can_fail: false,
                 skip_file_ext_check: true,
+                rule: None,
             }))
-        } else if url.scheme() == "https"
-            && !url
-                .domain()
-                .map_or(true, |d| self.do_not_fetch_domains.contains(d))
-        {
+        } else if url.scheme() == "https" && url.domain().is_some() {
+            // resource rules only apply to resources from outside cohost
+            let rule = self.resource_rules.rule_for(url);
+            if rule.map_or(false, |rule| rule.is_denied()) {
+                trace!("not fetching URL {url} because of a resource rule");
+                return Ok(None);
+            }
+
             let mut file_path = self.root_dir.clone();
             file_path.push("rc");
             file_path.push("external");
@@ -433,6 +741,7 @@ impl CohostContext {
                 file_path,
                 can_fail: true,
                 skip_file_ext_check: false,
+                rule,
             }))
         } else {
             trace!("ignoring URL {url}");
@@ -497,15 +806,41 @@ impl CohostContext {
         }
     }
 
+    /// Checks the contents of a resource that was already downloaded and stores the sniffed
+    /// content type, if it differs from the one stored before.
+    /// Returns true if it was changed.
+    pub async fn resniff_resource(&self, url: &Url, file_path: &Path) -> anyhow::Result<bool> {
+        let Some(props) = self.props_for_resource_url(url)? else {
+            return Ok(false);
+        };
+
+        let mut head = Vec::with_capacity(SNIFF_LENGTH);
+        fs::File::open(self.root_dir.join(file_path))?
+            .take(SNIFF_LENGTH as u64)
+            .read_to_end(&mut head)?;
+
+        let Some(sniffed) = sniff_content_type(&head) else {
+            return Ok(false);
+        };
+
+        let stored = self.get_res_content_type(&props.fetch).await?;
+        if stored.map_or(false, |stored| stored.starts_with(sniffed)) {
+            return Ok(false);
+        }
+
+        trace!("{url} is {sniffed}");
+        self.insert_res_content_type(&props.fetch, sniffed).await?;
+        Ok(true)
+    }
+
     /// Loads a resource to a file.
     /// Returns file path (relative to out dir) or None if it shouldn't be loaded
     pub async fn load_resource_to_file(
         &self,
         url: &Url,
-        state: &Mutex<CurrentStateV1>,
         loaded: Option<&mut bool>,
     ) -> Result<Option<PathBuf>, LoadResError> {
-        if state.lock().await.failed_urls.contains(&url.to_string()) {
+        if self.is_resource_failure_skipped(url).await? {
             return Ok(None);
         }
 
@@ -539,6 +874,9 @@ impl CohostContext {
             props.file_path.clone()
         };
 
+        // another task might have downloaded it while we were waiting
+        let _download_lock = self.lock_download(url).await;
+
         if !needs_content_type {
             if let Some(result) = self.get_url_file(&url).await? {
                 return Ok(Some(result));
@@ -555,50 +893,82 @@ impl CohostContext {
             return Ok(Some(result_file_path));
         }
 
-        let mut res = match self.get_file(url.clone()).await {
-            Ok(file) => file,
+        let (mut res, resume_from) = match self.get_file_resuming(url).await {
+            Ok(res) => res,
             Err(e) => {
-                if props.can_fail || !e.is_recoverable() {
-                    state.lock().await.failed_urls.push(url.to_string());
-                }
+                self.record_resource_failure(url, &e, props.can_fail)
+                    .await?;
                 Err(LoadResError::Get(e))?
             }
         };
 
-        let content_type = if let Some(content_type) = res
+        let header_content_type = res
             .headers()
             .get("content-type")
             .and_then(|value| value.to_str().ok().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        let max_size = props.rule.and_then(|rule| rule.max_size);
+
+        if let (Some(max_size), Some(len)) = (max_size, res.content_length()) {
+            let len = resume_from + len;
+            if len > max_size {
+                let reason = format!("{len} bytes is larger than the maximum of {max_size} bytes");
+                return self.skip_resource(url, reason).await;
+            }
+        }
+
+        let (etag, last_modified) = response_validators(&res);
+
+        let download = match self
+            .download_to_temp_file(url, &mut res, resume_from, max_size)
+            .await
         {
-            self.insert_res_content_type(&props.fetch, &content_type)
-                .await
-                .map_err(|e| LoadResError::Unknown(e.into()))?;
-            content_type
-        } else {
-            self.insert_res_content_type(&props.fetch, "")
-                .await
-                .map_err(|e| LoadResError::Unknown(e.into()))?;
-            String::new()
+            Ok(download) => download,
+            Err(LoadResError::Get(e)) => {
+                self.record_resource_failure(url, &e, props.can_fail)
+                    .await?;
+                Err(LoadResError::Get(e))?
+            }
+            Err(e) => Err(e)?,
+        };
+        let Some(download) = download else {
+            let reason = format!(
+                "it is larger than the maximum of {} bytes",
+                max_size.unwrap_or_default()
+            );
+            return self.skip_resource(url, reason).await;
         };
 
+        // servers often send a wrong or generic content type, so the sniffed one is preferred
+        let content_type = match sniff_content_type(&download.head) {
+            Some(sniffed) => {
+                if !header_content_type.starts_with(sniffed) {
+                    debug!("{url} is {sniffed}, not {header_content_type:?}");
+                }
+                sniffed.to_string()
+            }
+            None => header_content_type,
+        };
+
+        if let Some(rule) = props.rule {
+            if !rule.allows_content_type(&content_type) {
+                let reason =
+                    format!("content type {content_type:?} is not allowed by a resource rule");
+                return self.skip_resource(url, reason).await;
+            }
+        }
+
+        self.insert_res_content_type(&props.fetch, &content_type)
+            .await
+            .map_err(|e| LoadResError::Unknown(e.into()))?;
+
         let file_path_with_ext = if needs_file_extension {
             Self::add_content_type_ext(props.file_path, &content_type, true)
         } else {
             props.file_path
         };
 
-        let file = NamedTempFile::with_prefix_in("cohost-dl-res-", &self.temp_dir)
-            .context("creating temporary file")
-            .map_err(|e| LoadResError::Unknown(e.into()))?;
-
-        while let Some(chunk) = res
-            .chunk()
-            .await
-            .map_err(|e| LoadResError::Unknown(e.into()))?
-        {
-            file.as_file().write_all(&chunk)?;
-        }
-
         let result_file_path = file_path_with_ext
             .strip_prefix(&self.root_dir)
             .context("getting relative path")
@@ -609,16 +979,592 @@ impl CohostContext {
         file_path_dir.pop();
         fs::create_dir_all(file_path_dir)?;
 
-        file.persist(&file_path_with_ext)
+        download
+            .persist(&file_path_with_ext)
             .with_context(|| format!("moving resource to {}", file_path_with_ext.display()))
             .map_err(|e| LoadResError::Unknown(e.into()))?;
 
+        self.insert_resource_validators(
+            url,
+            etag.as_deref(),
+            last_modified.as_deref(),
+            download.size,
+            &download.sha256,
+        )
+        .await
+        .map_err(LoadResError::Unknown)?;
+        // it worked this time
+        self.remove_resource_failure(url.as_str())
+            .await
+            .map_err(LoadResError::Unknown)?;
+
         if let Some(loaded) = loaded {
             *loaded = true;
         }
 
         Ok(Some(result_file_path))
     }
+
+    /// Waits until no other task is downloading this resource.
+    async fn lock_download(&self, url: &Url) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut downloads = self.downloads.lock().unwrap();
+            downloads.retain(|_, lock| lock.strong_count() > 0);
+
+            match downloads.get(url.as_str()).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(Mutex::new(()));
+                    downloads.insert(url.to_string(), Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+
+    /// Returns the paths of the partial file and its metadata for a resource download.
+    /// Use [Self::lock_download] while using them.
+    fn partial_download_paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let name = hex::encode(Sha256::digest(url.as_str()));
+        let dir = self.temp_dir.join("partial");
+        (
+            dir.join(format!("{name}.part")),
+            dir.join(format!("{name}.json")),
+        )
+    }
+
+    /// Requests a resource, continuing a partial download from an earlier try if possible.
+    /// Returns the response and the number of bytes it starts at.
+    async fn get_file_resuming(&self, url: &Url) -> Result<(reqwest::Response, u64), GetError> {
+        let (part_path, meta_path) = self.partial_download_paths(url);
+
+        let partial = fs::read(&meta_path)
+            .ok()
+            .and_then(|meta| serde_json::from_slice::<PartialDownload>(&meta).ok())
+            .filter(|partial| partial.url == url.as_str());
+        let part_len = fs::metadata(&part_path).map_or(0, |meta| meta.len());
+
+        if let Some(validator) = partial.as_ref().and_then(|partial| partial.if_range()) {
+            if part_len > 0 {
+                trace!("GET {url} (from {part_len} bytes)");
+
+                let res = self
+                    .client
+                    .get(url.clone())
+                    .header(RANGE, format!("bytes={part_len}-"))
+                    .header(IF_RANGE, validator)
+                    .send()
+                    .await
+                    .map_err(|e| GetError::Req(url.clone(), e))?;
+
+                match res.status() {
+                    StatusCode::PARTIAL_CONTENT if content_range_start(&res) == Some(part_len) => {
+                        info!("resuming download of {url} at {part_len} bytes");
+                        return Ok((res, part_len));
+                    }
+                    // the file changed or the server doesn't want to. start over
+                    StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE => (),
+                    _ => {
+                        return Self::file_response(url.clone(), res)
+                            .await
+                            .map(|res| (res, 0))
+                    }
+                }
+            }
+        }
+
+        Ok((self.get_file(url.clone()).await?, 0))
+    }
+
+    /// Streams a response into a temporary file.
+    /// Returns None if it turns out to be larger than `max_size`.
+    ///
+    /// If the server supports range requests, the partial file is kept when the connection
+    /// fails, so that the next try can continue where this one stopped.
+    async fn download_to_temp_file(
+        &self,
+        url: &Url,
+        res: &mut reqwest::Response,
+        resume_from: u64,
+        max_size: Option<u64>,
+    ) -> Result<Option<DownloadedResource>, LoadResError> {
+        let (part_path, meta_path) = self.partial_download_paths(url);
+        fs::create_dir_all(part_path.parent().unwrap())?;
+
+        let mut hasher = Sha256::new();
+        let mut head = Vec::new();
+        let mut size = 0;
+
+        let mut file = if resume_from > 0 {
+            let mut file = fs::OpenOptions::new()
+                .read(true)
+                .append(true)
+                .open(&part_path)?;
+
+            // the data we already have also needs to be hashed
+            let mut buf = vec![0; 65536];
+            loop {
+                let len = file.read(&mut buf)?;
+                if len == 0 {
+                    break;
+                }
+                let chunk = &buf[..len];
+                if head.len() < SNIFF_LENGTH {
+                    let len = (SNIFF_LENGTH - head.len()).min(chunk.len());
+                    head.extend_from_slice(&chunk[..len]);
+                }
+                hasher.update(chunk);
+                size += len as u64;
+            }
+
+            file
+        } else {
+            let partial = PartialDownload::from_response(url, res);
+            let is_resumable = res
+                .headers()
+                .get(ACCEPT_RANGES)
+                .map_or(false, |value| value.as_bytes() == b"bytes")
+                && partial.if_range().is_some();
+
+            if is_resumable {
+                let meta = serde_json::to_vec(&partial)
+                    .context("serializing download info")
+                    .map_err(LoadResError::Unknown)?;
+                fs::write(&meta_path, meta)?;
+            } else {
+                let _ = fs::remove_file(&meta_path);
+            }
+
+            fs::File::create(&part_path)?
+        };
+
+        loop {
+            let chunk = match res.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    if !fs::exists(&meta_path)? {
+                        // can't be resumed
+                        let _ = fs::remove_file(&part_path);
+                    }
+                    return Err(LoadResError::Get(GetError::Req(url.clone(), e)));
+                }
+            };
+
+            // the content length might be missing or wrong
+            size += chunk.len() as u64;
+            if max_size.map_or(false, |max_size| size > max_size) {
+                drop(file);
+                let _ = fs::remove_file(&part_path);
+                let _ = fs::remove_file(&meta_path);
+                return Ok(None);
+            }
+
+            if head.len() < SNIFF_LENGTH {
+                let len = (SNIFF_LENGTH - head.len()).min(chunk.len());
+                head.extend_from_slice(&chunk[..len]);
+            }
+
+            hasher.update(&chunk);
+            file.write_all(&chunk)?;
+        }
+
+        let _ = fs::remove_file(&meta_path);
+
+        Ok(Some(DownloadedResource {
+            path: part_path,
+            head,
+            size,
+            sha256: hex::encode(hasher.finalize()),
+        }))
+    }
+
+    /// Checks a downloaded resource against its stored size and hash, and asks the server
+    /// whether it has changed.
+    ///
+    /// If it has changed (or the local file is broken), the new version is downloaded to a new
+    /// file next to the old one, which is kept.
+    /// With `broken_only`, only missing and broken files are downloaded again.
+    pub async fn revalidate_resource(
+        &self,
+        url: &Url,
+        file_path: &Path,
+        broken_only: bool,
+    ) -> anyhow::Result<Revalidation> {
+        let Some(props) = self.props_for_resource_url(url)? else {
+            return Ok(Revalidation::Skipped);
+        };
+
+        let _download_lock = self.lock_download(url).await;
+
+        let full_path = self.root_dir.join(file_path);
+        let local = if full_path.exists() {
+            Some(
+                hash_file(&full_path)
+                    .with_context(|| format!("reading {}", full_path.display()))?,
+            )
+        } else {
+            None
+        };
+        let validators = self.get_resource_validators(url).await?;
+
+        let is_broken = match (&local, &validators) {
+            (None, _) => true,
+            (Some((size, sha256)), Some(validators)) => {
+                *size != validators.size as u64 || *sha256 != validators.sha256
+            }
+            (Some(_), None) => false,
+        };
+
+        if broken_only && !is_broken {
+            if validators.is_some() {
+                self.touch_resource_validators(url).await?;
+                return Ok(Revalidation::Unchanged);
+            }
+
+            let (size, sha256) = local.as_ref().unwrap();
+            self.insert_resource_validators(url, None, None, *size, sha256)
+                .await?;
+            return Ok(Revalidation::Hashed);
+        }
+
+        let res = match &validators {
+            Some(validators)
+                if !is_broken
+                    && (validators.etag.is_some() || validators.last_modified.is_some()) =>
+            {
+                self.get_file_if_changed(
+                    &props.fetch,
+                    validators.etag.as_deref(),
+                    validators.last_modified.as_deref(),
+                )
+                .await
+            }
+            _ => self.get_file(props.fetch.clone()).await.map(Some),
+        };
+
+        let mut res = match res {
+            Ok(Some(res)) => res,
+            Ok(None) => {
+                self.touch_resource_validators(url).await?;
+                return Ok(Revalidation::Unchanged);
+            }
+            Err(GetError::NotFound(..)) => return Ok(Revalidation::Gone),
+            Err(GetError::OtherStatus(_, StatusCode::GONE, _)) => return Ok(Revalidation::Gone),
+            Err(e) => return Err(e.into()),
+        };
+
+        let (etag, last_modified) = response_validators(&res);
+        let max_size = props.rule.and_then(|rule| rule.max_size);
+        let Some(download) = self
+            .download_to_temp_file(url, &mut res, 0, max_size)
+            .await
+            .map_err(|e| anyhow!("{e}"))?
+        else {
+            bail!(
+                "new version is larger than the maximum of {} bytes",
+                max_size.unwrap_or_default()
+            );
+        };
+
+        self.insert_resource_validators(
+            url,
+            etag.as_deref(),
+            last_modified.as_deref(),
+            download.size,
+            &download.sha256,
+        )
+        .await?;
+
+        if local
+            .as_ref()
+            .map_or(false, |(_, sha256)| *sha256 == download.sha256)
+        {
+            // the stored validators were wrong
+            return Ok(Revalidation::Unchanged);
+        }
+
+        if let Some(content_type) = sniff_content_type(&download.head) {
+            self.insert_res_content_type(&props.fetch, content_type)
+                .await?;
+        }
+
+        if local.is_none() {
+            let mut file_path_dir = full_path.clone();
+            file_path_dir.pop();
+            fs::create_dir_all(file_path_dir)?;
+
+            download
+                .persist(&full_path)
+                .with_context(|| format!("moving resource to {}", full_path.display()))?;
+            return Ok(Revalidation::Restored);
+        }
+
+        let new_path = versioned_path(&full_path);
+        download
+            .persist(&new_path)
+            .with_context(|| format!("moving resource to {}", new_path.display()))?;
+
+        let new_path = new_path
+            .strip_prefix(&self.root_dir)
+            .context("getting relative path")?
+            .to_path_buf();
+
+        let old_sha256 = local.map(|(_, sha256)| sha256);
+        self.insert_resource_version(url, file_path, old_sha256.as_deref())
+            .await?;
+        self.insert_url_file(url, &new_path).await?;
+
+        Ok(Revalidation::Changed {
+            new_path,
+            was_broken: is_broken,
+        })
+    }
+
+    /// Remembers that a resource was rejected by a resource rule, so that it won't be fetched again.
+    async fn skip_resource(
+        &self,
+        url: &Url,
+        reason: String,
+    ) -> Result<Option<PathBuf>, LoadResError> {
+        warn!("not storing {url}: {reason}");
+        self.insert_resource_failure(url, "Rule", None, &reason, true)
+            .await
+            .map_err(LoadResError::Unknown)?;
+        Ok(None)
+    }
+
+    /// Records a failed download, which is skipped from now on if
+    /// [should_skip_failed_resource] says so.
+    async fn record_resource_failure(
+        &self,
+        url: &Url,
+        error: &GetError,
+        can_fail: bool,
+    ) -> Result<(), LoadResError> {
+        let attempts = self.resource_failure_attempts(url).await? + 1;
+        let skip = should_skip_failed_resource(error, can_fail, attempts);
+
+        self.insert_resource_failure(
+            url,
+            error.class(),
+            error.status().map(|status| status.as_u16()),
+            &error.to_string(),
+            skip,
+        )
+        .await
+        .map_err(LoadResError::Unknown)
+    }
+
+    /// Stores a resource that was already fetched elsewhere (e.g. in an imported archive).
+    /// Returns file path (relative to out dir) or None if it shouldn't be stored.
+    /// Resources that already have a file will not be overwritten.
+    pub async fn store_resource(
+        &self,
+        url: &Url,
+        content_type: &str,
+        mut data: impl Read,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let Some(props) = self.props_for_resource_url(url)? else {
+            return Ok(None);
+        };
+
+        if let Some(result) = self.get_url_file(url).await? {
+            return Ok(Some(result));
+        }
+
+        let file = NamedTempFile::with_prefix_in("cohost-dl-res-", &self.temp_dir)
+            .context("creating temporary file")?;
+
+        let max_size = props.rule.and_then(|rule| rule.max_size);
+        let mut hasher = Sha256::new();
+        let mut head = Vec::new();
+        let mut size = 0;
+
+        let mut buf = vec![0; 65536];
+        loop {
+            let len = data.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            let chunk = &buf[..len];
+
+            size += len as u64;
+            if max_size.map_or(false, |max_size| size > max_size) {
+                trace!("not storing {url} because of a resource rule");
+                return Ok(None);
+            }
+
+            if head.len() < SNIFF_LENGTH {
+                let len = (SNIFF_LENGTH - head.len()).min(chunk.len());
+                head.extend_from_slice(&chunk[..len]);
+            }
+
+            hasher.update(chunk);
+            file.as_file().write_all(chunk)?;
+        }
+
+        let content_type = sniff_content_type(&head).unwrap_or(content_type);
+
+        if let Some(rule) = props.rule {
+            if !rule.allows_content_type(content_type) {
+                trace!("not storing {url} because of a resource rule");
+                return Ok(None);
+            }
+        }
+
+        self.insert_res_content_type(&props.fetch, content_type)
+            .await?;
+
+        let needs_file_extension = !props.skip_file_ext_check
+            && does_resource_probably_need_a_file_extension(&props.file_path);
+        let file_path_with_ext = if needs_file_extension {
+            Self::add_content_type_ext(props.file_path, content_type, true)
+        } else {
+            props.file_path
+        };
+
+        let result_file_path = file_path_with_ext
+            .strip_prefix(&self.root_dir)
+            .context("getting relative path")?
+            .to_path_buf();
+
+        let mut file_path_dir = file_path_with_ext.clone();
+        file_path_dir.pop();
+        fs::create_dir_all(file_path_dir)?;
+
+        file.persist(&file_path_with_ext)
+            .with_context(|| format!("moving resource to {}", file_path_with_ext.display()))?;
+
+        let sha256 = hex::encode(hasher.finalize());
+        self.insert_resource_validators(url, None, None, size, &sha256)
+            .await?;
+
+        self.insert_url_file(url, &result_file_path).await?;
+
+        Ok(Some(result_file_path))
+    }
+}
+
+/// A resource downloaded to a temporary file, which is deleted unless it's persisted.
+struct DownloadedResource {
+    path: PathBuf,
+    /// The first few bytes for sniffing the file type
+    head: Vec<u8>,
+    size: u64,
+    sha256: String,
+}
+
+impl DownloadedResource {
+    fn persist(&self, to: &Path) -> std::io::Result<()> {
+        fs::rename(&self.path, to)
+    }
+}
+
+impl Drop for DownloadedResource {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Stored next to a partially downloaded file, so that the download can be resumed later.
+#[derive(Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PartialDownload {
+    fn from_response(url: &Url, res: &reqwest::Response) -> Self {
+        let (etag, last_modified) = response_validators(res);
+        Self {
+            url: url.to_string(),
+            etag,
+            last_modified,
+        }
+    }
+
+    /// Returns the validator for the If-Range header, which can't be a weak ETag.
+    fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// Returns where the content starts in a 206 response.
+fn content_range_start(res: &reqwest::Response) -> Option<u64> {
+    let range = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Result of revalidating a resource.
+#[derive(Debug)]
+pub enum Revalidation {
+    /// The resource is not fetched because of a resource rule (or it never was)
+    Skipped,
+    /// The size and hash of the local file were stored for the first time
+    Hashed,
+    Unchanged,
+    /// The file was missing and was downloaded again
+    Restored,
+    /// There is a new version at this path
+    Changed {
+        new_path: PathBuf,
+        was_broken: bool,
+    },
+    /// The server doesn't have the resource anymore
+    Gone,
+}
+
+/// Returns ETag and Last-Modified headers.
+fn response_validators(res: &reqwest::Response) -> (Option<String>, Option<String>) {
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    (header(ETAG), header(LAST_MODIFIED))
+}
+
+/// Returns size and SHA-256 hash of a file.
+fn hash_file(path: &Path) -> std::io::Result<(u64, String)> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)?;
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Returns a path next to the given one for a new version of the file, like `image.v2.png`.
+fn versioned_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // don't stack version numbers
+    let stem = match stem.rsplit_once(".v") {
+        Some((base, version)) if version.chars().all(|c| c.is_ascii_digit()) => base.to_string(),
+        _ => stem,
+    };
+
+    for version in 2.. {
+        let mut file_name = format!("{stem}.v{version}");
+        if let Some(ext) = path.extension() {
+            file_name.push('.');
+            file_name.push_str(&ext.to_string_lossy());
+        }
+
+        let path = path.with_file_name(file_name);
+        if !path.exists() {
+            return path;
+        }
+    }
+    unreachable!()
 }
 
 pub const KNOWN_FILE_EXTENSIONS: &[(&str, &[&str])] = &[
@@ -650,6 +1596,9 @@ pub const KNOWN_FILE_EXTENSIONS: &[(&str, &[&str])] = &[
         "wav",
         &["audio/wav", "audio/vnd.wave", "audio/wave", "audio/x-wav"],
     ),
+    ("webm", &["video/webm", "audio/webm"]),
+    ("mov", &["video/quicktime"]),
+    ("mkv", &["video/x-matroska"]),
     // other resources
     ("css", &["text/css"]),
     ("js", &["application/javascript", "text/javascript"]),
@@ -658,6 +1607,11 @@ pub const KNOWN_FILE_EXTENSIONS: &[(&str, &[&str])] = &[
     ("map", &[]),
     ("woff", &["font/woff"]),
     ("woff2", &["font/woff2"]),
+    ("ttf", &["font/ttf"]),
+    ("otf", &["font/otf"]),
+    // documents and archives
+    ("pdf", &["application/pdf"]),
+    ("zip", &["application/zip", "application/x-zip-compressed"]),
 ];
 
 fn does_resource_probably_need_a_file_extension(path: &Path) -> bool {
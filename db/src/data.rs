@@ -2,6 +2,7 @@ use crate::comment::CommentFromCohost;
 use crate::context::{CohostContext, GetError};
 use crate::dl::CurrentStateV1;
 use crate::feed::TagRelationship;
+use crate::link_ref::LinkRefs;
 use crate::post::{LimitedVisibilityReason, PostBlock, PostFromCohost, PostState};
 use crate::project::{
     AvatarShape, LoggedOutPostVisibility, ProjectAskSettings, ProjectContactCard, ProjectFlag,
@@ -10,12 +11,13 @@ use crate::project::{
 use crate::res_ref::ResourceRefs;
 use crate::trpc::{LoginLoggedIn, SinglePost};
 use anyhow::Context;
+use chrono::Utc;
 use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
 use diesel::{Insertable, RunQueryDsl};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::str;
@@ -24,6 +26,10 @@ use tokio::sync::Mutex;
 
 pub struct Database {
     db: Mutex<SqliteConnection>,
+    /// Projects whose content is treated as if it wasn't archived (see [Database::hide_non_public_projects])
+    hidden_projects: Vec<i32>,
+    /// Handles of hidden projects, including ones that weren't archived
+    hidden_handles: Vec<String>,
 }
 
 /// Select fields from posts to store in the database blob
@@ -186,6 +192,39 @@ pub struct DbProject {
     pub data_version: i32,
 }
 
+#[derive(Queryable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::resource_validators)]
+#[diesel(treat_none_as_null = true)]
+pub struct DbResourceValidators {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub size: i64,
+    pub sha256: String,
+    pub checked_at: String,
+}
+
+#[derive(Queryable)]
+pub struct DbResourceFailure {
+    pub url: String,
+    pub error_class: String,
+    pub status_code: Option<i32>,
+    pub message: String,
+    pub attempts: i32,
+    pub first_failed_at: String,
+    pub last_failed_at: String,
+    pub skip: bool,
+}
+
+/// Where a resource is used: (post ID, post author handle), (project ID, handle), and
+/// (comment ID, post ID, post author handle).
+#[derive(Debug, Default)]
+pub struct ResourceReferences {
+    pub posts: Vec<(u64, String)>,
+    pub projects: Vec<(u64, String)>,
+    pub comments: Vec<(String, u64, String)>,
+}
+
 #[derive(Queryable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::schema::comments)]
 pub struct DbComment {
@@ -198,6 +237,18 @@ pub struct DbComment {
     pub data_version: i32,
 }
 
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::asks)]
+pub struct DbAsk {
+    pub id: String,
+    pub post_id: i32,
+    pub asking_project_id: Option<i32>,
+    pub asking_handle: Option<String>,
+    pub anon: bool,
+    pub logged_in: bool,
+    pub sent_at: String,
+}
+
 #[derive(Debug, Error)]
 pub enum DbDataError {
     #[error(transparent)]
@@ -235,6 +286,31 @@ impl DbPost {
     }
 }
 
+impl DbAsk {
+    /// Collects the asks in a post's blocks. The asker isn't stored for anonymous asks.
+    fn from_blocks(post_id: u64, blocks: &[PostBlock]) -> Vec<Self> {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                PostBlock::Ask { ask } => Some(ask),
+                _ => None,
+            })
+            .map(|ask| {
+                let asking_project = ask.asking_project.as_ref().filter(|_| !ask.anon);
+                Self {
+                    id: ask.ask_id.clone(),
+                    post_id: post_id as i32,
+                    asking_project_id: asking_project.map(|p| p.project_id as i32),
+                    asking_handle: asking_project.map(|p| p.handle.clone()),
+                    anon: ask.anon,
+                    logged_in: ask.logged_in,
+                    sent_at: ask.sent_at.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
 impl DbProject {
     fn from_project(project: &ProjectFromCohost, data: Vec<u8>, data_version: i32) -> Self {
         Self {
@@ -287,6 +363,8 @@ impl Database {
     pub fn new(conn: SqliteConnection) -> Self {
         Self {
             db: Mutex::new(conn),
+            hidden_projects: Vec::new(),
+            hidden_handles: Vec::new(),
         }
     }
 
@@ -295,6 +373,101 @@ impl Database {
     }
 }
 
+/// Public mirror
+impl Database {
+    /// Hides projects that were private or only visible to logged-in users on cohost, as well as
+    /// the projects in `opt_out`, from all queries used for showing the archive.
+    /// Their posts and comments, and shares of their posts, are treated as if they weren't archived.
+    pub async fn hide_non_public_projects(&mut self, opt_out: &[String]) -> QueryResult<()> {
+        use crate::schema::projects::dsl::*;
+
+        let opt_out: Vec<_> = opt_out
+            .iter()
+            .map(|h| h.trim_start_matches('@').to_lowercase())
+            .collect();
+
+        let db = self.db.get_mut();
+        let all_projects: Vec<(i32, String, bool, bool)> = projects
+            .select((id, handle, is_private, requires_logged_in))
+            .load(db)?;
+
+        let mut hidden_projects = Vec::new();
+        let mut hidden_handles = Vec::new();
+        for (project_id, project_handle, private, logged_in_only) in all_projects {
+            if private || logged_in_only || opt_out.contains(&project_handle.to_lowercase()) {
+                hidden_projects.push(project_id);
+                hidden_handles.push(project_handle);
+            }
+        }
+        // opted-out projects might still show up as the sender of an ask
+        for opt_out_handle in opt_out {
+            if !hidden_handles
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(&opt_out_handle))
+            {
+                hidden_handles.push(opt_out_handle);
+            }
+        }
+
+        self.hidden_projects = hidden_projects;
+        self.hidden_handles = hidden_handles;
+        Ok(())
+    }
+
+    pub fn is_project_hidden(&self, project_id: u64) -> bool {
+        self.hidden_projects.contains(&(project_id as i32))
+    }
+
+    pub fn is_handle_hidden(&self, project_handle: &str) -> bool {
+        self.hidden_handles
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(project_handle))
+    }
+
+    /// Returns true if the resource is only used by hidden projects.
+    /// Resources that aren't used by anything are not hidden.
+    pub async fn is_resource_hidden(&self, the_url: &Url) -> QueryResult<bool> {
+        use crate::schema::{
+            comment_resources, comments, post_resources, posts, project_resources,
+        };
+
+        if self.hidden_projects.is_empty() {
+            return Ok(false);
+        }
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let the_url = the_url.to_string();
+
+        let post_refs: Vec<i32> = post_resources::table
+            .inner_join(posts::table)
+            .filter(post_resources::url.eq(&the_url))
+            .select(posts::posting_project_id)
+            .load(db)?;
+        let project_refs: Vec<i32> = project_resources::table
+            .filter(project_resources::url.eq(&the_url))
+            .select(project_resources::project_id)
+            .load(db)?;
+        let comment_refs: Vec<(Option<i32>, i32)> = comment_resources::table
+            .inner_join(comments::table.inner_join(posts::table))
+            .filter(comment_resources::url.eq(&the_url))
+            .select((comments::posting_project_id, posts::posting_project_id))
+            .load(db)?;
+
+        let is_hidden = |project: &i32| self.hidden_projects.contains(project);
+
+        let used = !post_refs.is_empty() || !project_refs.is_empty() || !comment_refs.is_empty();
+        let visible = post_refs.iter().any(|p| !is_hidden(p))
+            || project_refs.iter().any(|p| !is_hidden(p))
+            || comment_refs.iter().any(|(commenter, post_project)| {
+                !commenter.as_ref().is_some_and(is_hidden) && !is_hidden(post_project)
+            });
+
+        Ok(used && !visible)
+    }
+}
+
 /// Project queries
 impl Database {
     pub async fn followed_by_any(&self) -> anyhow::Result<Vec<u64>> {
@@ -307,13 +480,47 @@ impl Database {
         Ok(result.into_iter().map(|i| i as u64).collect())
     }
 
+    /// Returns all projects that this project has directly shared posts from.
+    pub async fn projects_shared_from(&self, project_id: u64) -> QueryResult<Vec<u64>> {
+        use crate::schema::posts::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let shared_posts: Vec<Option<i32>> = posts
+            .filter(posting_project_id.eq(project_id as i32))
+            .filter(share_of_post_id.is_not_null())
+            .select(share_of_post_id)
+            .distinct()
+            .get_results(db)?;
+        let shared_posts: Vec<i32> = shared_posts.into_iter().flatten().collect();
+
+        let mut projects = HashSet::new();
+        for chunk in shared_posts.chunks(1000) {
+            let chunk_projects: Vec<i32> = posts
+                .filter(id.eq_any(chunk))
+                .filter(posting_project_id.ne(project_id as i32))
+                .select(posting_project_id)
+                .distinct()
+                .get_results(db)?;
+            projects.extend(chunk_projects.into_iter().map(|i| i as u64));
+        }
+
+        let mut projects: Vec<_> = projects.into_iter().collect();
+        projects.sort();
+        Ok(projects)
+    }
+
     pub async fn project(&self, project_id: u64) -> QueryResult<DbProject> {
         use crate::schema::projects::dsl::*;
 
         let mut db = self.db.lock().await;
         let db = &mut *db;
 
-        Ok(projects.filter(id.eq(project_id as i32)).first(db)?)
+        Ok(projects
+            .filter(id.eq(project_id as i32))
+            .filter(id.ne_all(&self.hidden_projects))
+            .first(db)?)
     }
 
     pub async fn project_for_handle(&self, project_handle: &str) -> anyhow::Result<DbProject> {
@@ -322,7 +529,10 @@ impl Database {
         let mut db = self.db.lock().await;
         let db = &mut *db;
 
-        Ok(projects.filter(handle.eq(project_handle)).first(db)?)
+        Ok(projects
+            .filter(handle.eq(project_handle))
+            .filter(id.ne_all(&self.hidden_projects))
+            .first(db)?)
     }
 
     pub async fn project_id_for_handle(&self, project_handle: &str) -> QueryResult<u64> {
@@ -333,6 +543,7 @@ impl Database {
 
         let result: i32 = projects
             .filter(handle.eq(project_handle))
+            .filter(id.ne_all(&self.hidden_projects))
             .select(id)
             .first(db)?;
         Ok(result as u64)
@@ -345,6 +556,7 @@ impl Database {
 
         let count: i64 = projects
             .filter(id.eq(project_id as i32))
+            .filter(id.ne_all(&self.hidden_projects))
             .count()
             .get_result(db)?;
         Ok(count > 0)
@@ -357,6 +569,7 @@ impl Database {
 
         let count: i64 = projects
             .filter(handle.eq(project_handle))
+            .filter(id.ne_all(&self.hidden_projects))
             .count()
             .get_result(db)?;
         Ok(count > 0)
@@ -370,6 +583,7 @@ impl Database {
 
         projects::projects
             .filter(projects::id.eq_any(posts::posts.select(posts::posting_project_id)))
+            .filter(projects::id.ne_all(&self.hidden_projects))
             .order_by(projects::handle)
             .select(projects::handle)
             .load(db)
@@ -384,6 +598,7 @@ impl Database {
 
         projects::projects
             .filter(projects::id.eq_any(likes::likes.select(likes::from_project_id)))
+            .filter(projects::id.ne_all(&self.hidden_projects))
             .order_by(projects::handle)
             .select(projects::handle)
             .load(db)
@@ -398,12 +613,54 @@ impl Database {
 
         projects::projects
             .filter(projects::id.eq_any(follows::follows.select(follows::from_project_id)))
+            .filter(projects::id.ne_all(&self.hidden_projects))
             .order_by(projects::handle)
             .select(projects::handle)
             .load(db)
     }
 }
 
+/// Ask counts for one project.
+#[derive(Debug, Default, Serialize)]
+pub struct AskSummary {
+    pub answered: u64,
+    pub answered_anon: u64,
+    /// Non-anonymous asks sent to others
+    pub sent: u64,
+    /// Projects whose non-anonymous asks were answered most often
+    pub top_askers: Vec<(String, u64)>,
+}
+
+/// An archived post that shares another post.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostShare {
+    pub post_id: u64,
+    pub share_of_post_id: u64,
+    pub handle: String,
+    pub filename: String,
+    pub published_at: Option<String>,
+    /// If false, the share adds its own content
+    pub is_transparent_share: bool,
+}
+
+/// An archived post that mentions or links to something.
+#[derive(Debug, Clone, Serialize)]
+pub struct Backlink {
+    pub post_id: u64,
+    pub handle: String,
+    pub filename: String,
+    pub published_at: Option<String>,
+}
+
+/// The most recent posts by others mentioning a project or linking to its posts.
+#[derive(Debug, Serialize)]
+pub struct ProjectBacklinks {
+    pub mentioned_in: Vec<Backlink>,
+    pub mention_count: u64,
+    pub linked_from: Vec<Backlink>,
+    pub link_count: u64,
+}
+
 /// Post queries
 impl Database {
     pub async fn has_post(&self, post_id: u64) -> QueryResult<bool> {
@@ -461,7 +718,10 @@ impl Database {
         let mut db = self.db.lock().await;
         let db = &mut *db;
 
-        Ok(posts.filter(id.eq(post_id as i32)).first(db)?)
+        Ok(posts
+            .filter(id.eq(post_id as i32))
+            .filter(posting_project_id.ne_all(&self.hidden_projects))
+            .first(db)?)
     }
 
     pub async fn total_post_count(&self) -> anyhow::Result<u64> {
@@ -577,6 +837,7 @@ impl Database {
 
         let items = posts
             .filter(share_of_post_id.eq(post_id as i32))
+            .filter(posting_project_id.ne_all(&self.hidden_projects))
             .select(id)
             .load_iter::<i32, _>(db)?;
 
@@ -587,6 +848,212 @@ impl Database {
         Ok(res_items)
     }
 
+    /// Returns the number of archived posts that directly share each post. Posts without shares
+    /// are not included.
+    pub async fn share_counts(&self, post_ids: &[u64]) -> QueryResult<HashMap<u64, u64>> {
+        use crate::schema::posts::dsl::*;
+        use diesel::dsl::count_star;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let post_ids: Vec<_> = post_ids.iter().map(|post| *post as i32).collect();
+
+        let counts: Vec<(Option<i32>, i64)> = posts
+            .filter(share_of_post_id.eq_any(post_ids))
+            .filter(posting_project_id.ne_all(&self.hidden_projects))
+            .group_by(share_of_post_id)
+            .select((share_of_post_id, count_star()))
+            .load(db)?;
+
+        Ok(counts
+            .into_iter()
+            .filter_map(|(post, count)| Some((post? as u64, count as u64)))
+            .collect())
+    }
+
+    /// Returns all archived posts that directly share any of the given posts, oldest first.
+    pub async fn shares_of_posts(&self, post_ids: &[u64]) -> QueryResult<Vec<PostShare>> {
+        use crate::schema::{posts, projects};
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let post_ids: Vec<_> = post_ids.iter().map(|post| *post as i32).collect();
+
+        let items: Vec<(i32, Option<i32>, String, String, Option<String>, bool)> = posts::table
+            .inner_join(projects::table)
+            .filter(posts::share_of_post_id.eq_any(post_ids))
+            .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+            .order_by((posts::published_at.asc(), posts::id.asc()))
+            .select((
+                posts::id,
+                posts::share_of_post_id,
+                projects::handle,
+                posts::filename,
+                posts::published_at,
+                posts::is_transparent_share,
+            ))
+            .load(db)?;
+
+        Ok(items
+            .into_iter()
+            .filter_map(
+                |(post, share_of, handle, filename, published_at, is_transparent_share)| {
+                    Some(PostShare {
+                        post_id: post as u64,
+                        share_of_post_id: share_of? as u64,
+                        handle,
+                        filename,
+                        published_at,
+                        is_transparent_share,
+                    })
+                },
+            )
+            .collect())
+    }
+
+    /// Returns the IDs of all archived posts that link to a post.
+    pub async fn posts_linking_to(&self, post_id: u64) -> QueryResult<Vec<u64>> {
+        use crate::schema::post_links;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let items: Vec<i32> = post_links::table
+            .filter(post_links::linked_post_id.eq(post_id as i32))
+            .select(post_links::post_id)
+            .load(db)?;
+
+        Ok(items.into_iter().map(|i| i as u64).collect())
+    }
+
+    /// Returns the given posts as backlinks, newest first.
+    pub async fn backlinks(&self, post_ids: &[u64]) -> QueryResult<Vec<Backlink>> {
+        use crate::schema::{posts, projects};
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let post_ids: Vec<_> = post_ids.iter().map(|post| *post as i32).collect();
+
+        let items: Vec<(i32, String, String, Option<String>)> = posts::table
+            .inner_join(projects::table)
+            .filter(posts::id.eq_any(post_ids))
+            .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+            .order_by((posts::published_at.desc(), posts::id.desc()))
+            .select((
+                posts::id,
+                projects::handle,
+                posts::filename,
+                posts::published_at,
+            ))
+            .load(db)?;
+
+        Ok(items
+            .into_iter()
+            .map(|(post, handle, filename, published_at)| Backlink {
+                post_id: post as u64,
+                handle,
+                filename,
+                published_at,
+            })
+            .collect())
+    }
+
+    pub async fn project_backlinks(
+        &self,
+        project_id: u64,
+        limit: u64,
+    ) -> QueryResult<ProjectBacklinks> {
+        let mentions = PostQuery {
+            mentions_project: Some(project_id),
+            limit,
+            ..Default::default()
+        };
+        let links = PostQuery {
+            links_to_project: Some(project_id),
+            limit,
+            ..Default::default()
+        };
+
+        Ok(ProjectBacklinks {
+            mentioned_in: self.backlinks(&mentions.get(self).await?).await?,
+            mention_count: mentions.count(self).await?,
+            linked_from: self.backlinks(&links.get(self).await?).await?,
+            link_count: links.count(self).await?,
+        })
+    }
+
+    pub async fn ask_summary(&self, project_id: u64) -> QueryResult<AskSummary> {
+        use crate::schema::{asks, posts};
+        use diesel::dsl::count_star;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let answered = asks::table
+            .inner_join(posts::table)
+            .filter(posts::posting_project_id.eq(project_id as i32));
+
+        let answered_count: i64 = answered.clone().count().get_result(db)?;
+        let answered_anon: i64 = answered
+            .clone()
+            .filter(asks::anon.eq(true))
+            .count()
+            .get_result(db)?;
+        let sent: i64 = asks::table
+            .inner_join(posts::table)
+            .filter(asks::asking_project_id.eq(project_id as i32))
+            .filter(asks::anon.eq(false))
+            .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+            .count()
+            .get_result(db)?;
+
+        let top_askers: Vec<(Option<String>, i64)> = answered
+            .filter(asks::anon.eq(false))
+            .filter(asks::asking_handle.is_not_null())
+            .filter(asks::asking_handle.ne_all(&self.hidden_handles))
+            .group_by(asks::asking_handle)
+            .order_by(count_star().desc())
+            .select((asks::asking_handle, count_star()))
+            .limit(10)
+            .load(db)?;
+
+        Ok(AskSummary {
+            answered: answered_count as u64,
+            answered_anon: answered_anon as u64,
+            sent: sent as u64,
+            top_askers: top_askers
+                .into_iter()
+                .filter_map(|(handle, count)| Some((handle?, count as u64)))
+                .collect(),
+        })
+    }
+
+    /// Looks up a project by the handle it used for sending asks. This also finds projects that
+    /// were not archived themselves.
+    pub async fn asking_project_id_for_handle(&self, handle: &str) -> QueryResult<u64> {
+        use crate::schema::asks::dsl::*;
+
+        if self.is_handle_hidden(handle) {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let project: Option<i32> = asks
+            .filter(asking_handle.eq(handle))
+            .filter(asking_project_id.is_not_null())
+            .select(asking_project_id)
+            .first(db)?;
+        project
+            .map(|p| p as u64)
+            .filter(|p| !self.is_project_hidden(*p))
+            .ok_or(diesel::result::Error::NotFound)
+    }
+
     pub async fn get_post_tags(&self, the_post_id: u64) -> QueryResult<Vec<String>> {
         use crate::schema::post_tags::dsl::*;
 
@@ -624,12 +1091,31 @@ impl Database {
         let (id, handle): (i32, String) = projects::projects
             .inner_join(posts::posts)
             .filter(posts::id.eq(post_id as i32))
+            .filter(projects::id.ne_all(&self.hidden_projects))
             .select((projects::id, projects::handle))
             .first(db)?;
 
         Ok((id as u64, handle))
     }
 
+    /// Returns the current handle of the posting project and the file name of a post, which make
+    /// up its URL.
+    pub async fn post_location(&self, post_id: u64) -> QueryResult<Option<(String, String)>> {
+        use crate::schema::posts::dsl as posts;
+        use crate::schema::projects::dsl as projects;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        projects::projects
+            .inner_join(posts::posts)
+            .filter(posts::id.eq(post_id as i32))
+            .filter(projects::id.ne_all(&self.hidden_projects))
+            .select((projects::handle, posts::filename))
+            .first(db)
+            .optional()
+    }
+
     pub async fn total_comment_count(&self) -> anyhow::Result<u64> {
         use crate::schema::comments::dsl::*;
 
@@ -646,7 +1132,14 @@ impl Database {
         let mut db = self.db.lock().await;
         let db = &mut *db;
 
-        comments.filter(id.eq(comment_id)).first(db)
+        comments
+            .filter(id.eq(comment_id))
+            .filter(
+                posting_project_id
+                    .is_null()
+                    .or(posting_project_id.ne_all(&self.hidden_projects)),
+            )
+            .first(db)
     }
 
     pub async fn get_comments(&self, the_post_id: u64) -> QueryResult<Vec<DbComment>> {
@@ -657,10 +1150,60 @@ impl Database {
 
         comments
             .filter(post_id.eq(the_post_id as i32))
+            .filter(
+                posting_project_id
+                    .is_null()
+                    .or(posting_project_id.ne_all(&self.hidden_projects)),
+            )
             .order_by(published_at)
             .load(db)
     }
 
+    /// Returns comments written by the project, newest first.
+    pub async fn get_project_comments(
+        &self,
+        project_id: u64,
+        offset: u64,
+        limit: u64,
+    ) -> QueryResult<Vec<DbComment>> {
+        use crate::schema::comments::dsl::*;
+        use crate::schema::posts::dsl as posts;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let hidden_posts = posts::posts
+            .filter(posts::posting_project_id.eq_any(&self.hidden_projects))
+            .select(posts::id);
+
+        comments
+            .filter(posting_project_id.eq(project_id as i32))
+            .filter(post_id.ne_all(hidden_posts))
+            .order_by(published_at.desc())
+            .offset(offset as i64)
+            .limit(limit as i64)
+            .load(db)
+    }
+
+    pub async fn project_comment_count(&self, project_id: u64) -> QueryResult<u64> {
+        use crate::schema::comments::dsl::*;
+        use crate::schema::posts::dsl as posts;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let hidden_posts = posts::posts
+            .filter(posts::posting_project_id.eq_any(&self.hidden_projects))
+            .select(posts::id);
+
+        let count: i64 = comments
+            .filter(posting_project_id.eq(project_id as i32))
+            .filter(post_id.ne_all(hidden_posts))
+            .count()
+            .get_result(db)?;
+        Ok(count as u64)
+    }
+
     pub async fn get_comment_ids(&self, offset: i64, limit: i64) -> QueryResult<Vec<String>> {
         use crate::schema::comments::dsl::*;
         let mut db = self.db.lock().await;
@@ -669,6 +1212,51 @@ impl Database {
         comments.select(id).offset(offset).limit(limit).load(db)
     }
 
+    /// Counts published posts by the start of their publishing date, which is `date_len` characters
+    /// long (e.g. 7 for `2024-01`). Only dates starting with `prefix` are included.
+    ///
+    /// Dates are in UTC.
+    pub async fn post_counts_by_date(
+        &self,
+        project: Option<u64>,
+        prefix: &str,
+        date_len: usize,
+    ) -> QueryResult<Vec<(String, u64)>> {
+        use crate::schema::posts::dsl::*;
+        use diesel::dsl::{count_star, sql};
+        use diesel::sql_types::Text;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let date = sql::<Text>(&format!("substr(published_at, 1, {date_len})"));
+
+        // a range instead of LIKE, so the index can be used
+        let mut query = posts
+            .filter(published_at.ge(prefix))
+            .filter(posting_project_id.ne_all(&self.hidden_projects))
+            .group_by(date.clone())
+            .order_by(date.clone())
+            .select((date, count_star()))
+            .into_boxed();
+
+        let mut prefix_end = prefix.to_string();
+        if let Some(last) = prefix_end.pop() {
+            let next = char::from_u32(last as u32 + 1).unwrap_or(char::MAX);
+            prefix_end.push(next);
+            query = query.filter(published_at.lt(prefix_end));
+        }
+        if let Some(project) = project {
+            query = query.filter(posting_project_id.eq(project as i32));
+        }
+
+        let counts: Vec<(String, i64)> = query.load(db)?;
+        Ok(counts
+            .into_iter()
+            .map(|(date, count)| (date, count as u64))
+            .collect())
+    }
+
     pub async fn has_comment(&self, comment_id: &str) -> QueryResult<bool> {
         use crate::schema::comments::dsl::*;
         let mut db = self.db.lock().await;
@@ -679,7 +1267,7 @@ impl Database {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PostQuery {
     pub posting_project_id: Option<u64>,
     pub share_of_post_id: Option<u64>,
@@ -688,10 +1276,20 @@ pub struct PostQuery {
     pub include_tags: Vec<String>,
     pub exclude_tags: Vec<String>,
     pub is_ask: Option<bool>,
+    /// Only posts answering a non-anonymous ask from this project
+    pub asked_by: Option<u64>,
+    /// Only posts by other projects that mention this project
+    pub mentions_project: Option<u64>,
+    /// Only posts by other projects that link to a post by this project
+    pub links_to_project: Option<u64>,
     pub is_adult: Option<bool>,
     pub is_reply: Option<bool>,
     pub is_share: Option<bool>,
     pub is_pinned: Option<bool>,
+    /// Inclusive lower bound for the publishing date (can also be just a date, like `2024-01-31`)
+    pub published_after: Option<String>,
+    /// Exclusive upper bound for the publishing date
+    pub published_before: Option<String>,
     pub offset: u64,
     pub limit: u64,
 }
@@ -706,10 +1304,15 @@ impl Default for PostQuery {
             include_tags: Vec::new(),
             exclude_tags: Vec::new(),
             is_ask: None,
+            asked_by: None,
+            mentions_project: None,
+            links_to_project: None,
             is_adult: None,
             is_reply: None,
             is_share: None,
             is_pinned: None,
+            published_after: None,
+            published_before: None,
             offset: 0,
             limit: 20,
         }
@@ -719,15 +1322,20 @@ impl Default for PostQuery {
 impl PostQuery {
     fn build(
         &self,
+        hidden_projects: &[i32],
     ) -> diesel::internal::table_macro::BoxedSelectStatement<
         diesel::sql_types::Integer,
         diesel::internal::table_macro::FromClause<crate::schema::posts::table>,
         diesel::sqlite::Sqlite,
     > {
+        use crate::schema::asks::dsl as asks;
         use crate::schema::follows::dsl as follows;
         use crate::schema::likes::dsl as likes;
+        use crate::schema::mentions::dsl as mentions;
+        use crate::schema::post_links::dsl as post_links;
         use crate::schema::post_tags::dsl as tags;
         use crate::schema::posts::dsl as posts;
+        use crate::schema::projects::dsl as projects;
         use crate::schema::related_tags::dsl as rel_tags;
 
         let mut query = posts::posts
@@ -741,6 +1349,25 @@ impl PostQuery {
             query = query.filter(posts::share_of_post_id.eq(share_of_post_id as i32));
         }
 
+        if !hidden_projects.is_empty() {
+            // shares of shares of hidden posts can only be found while rendering
+            let hidden_posts = diesel::alias!(crate::schema::posts as hidden_posts);
+            let hidden_post_ids = hidden_posts
+                .filter(
+                    hidden_posts
+                        .field(posts::posting_project_id)
+                        .eq_any(hidden_projects.to_vec()),
+                )
+                .select(hidden_posts.field(posts::id).nullable());
+            query = query
+                .filter(posts::posting_project_id.ne_all(hidden_projects.to_vec()))
+                .filter(
+                    posts::share_of_post_id
+                        .is_null()
+                        .or(posts::share_of_post_id.ne_all(hidden_post_ids)),
+                );
+        }
+
         let exclude_synonyms_1 = rel_tags::related_tags
             .filter(rel_tags::tag1.eq_any(self.exclude_tags.clone()))
             .filter(rel_tags::is_synonym.eq(1))
@@ -817,6 +1444,51 @@ impl PostQuery {
             }
         }
 
+        if let Some(asked_by) = self.asked_by {
+            let asked = asks::asks
+                .filter(asks::asking_project_id.eq(asked_by as i32))
+                .filter(asks::anon.eq(false))
+                .select(asks::post_id);
+            query = query.filter(posts::id.eq_any(asked));
+        }
+
+        if let Some(project) = self.mentions_project {
+            let handle = projects::projects
+                .filter(projects::id.eq(project as i32))
+                .select(projects::handle);
+            let mentioning = mentions::mentions
+                .filter(mentions::handle.eq_any(handle))
+                .select(mentions::post_id);
+            query = query
+                .filter(posts::posting_project_id.ne(project as i32))
+                .filter(posts::id.eq_any(mentioning));
+        }
+
+        if let Some(project) = self.links_to_project {
+            // links to posts that weren't archived can only be matched by handle
+            let handle = projects::projects
+                .filter(projects::id.eq(project as i32))
+                .select(projects::handle);
+            let linked_posts = diesel::alias!(crate::schema::posts as linked_posts);
+            let project_posts = linked_posts
+                .filter(
+                    linked_posts
+                        .field(posts::posting_project_id)
+                        .eq(project as i32),
+                )
+                .select(linked_posts.field(posts::id));
+            let linking = post_links::post_links
+                .filter(
+                    post_links::linked_post_id
+                        .eq_any(project_posts)
+                        .or(post_links::linked_handle.eq_any(handle)),
+                )
+                .select(post_links::post_id);
+            query = query
+                .filter(posts::posting_project_id.ne(project as i32))
+                .filter(posts::id.eq_any(linking));
+        }
+
         if let Some(is_reply) = self.is_reply {
             if is_reply {
                 query = query
@@ -842,14 +1514,22 @@ impl PostQuery {
             query = query.filter(posts::is_pinned.eq(is_pinned));
         }
 
+        if let Some(published_after) = &self.published_after {
+            query = query.filter(posts::published_at.ge(published_after.clone()));
+        }
+        if let Some(published_before) = &self.published_before {
+            query = query.filter(posts::published_at.lt(published_before.clone()));
+        }
+
         query.select(posts::id)
     }
 
     pub async fn get(&self, db: &Database) -> QueryResult<Vec<u64>> {
+        let hidden_projects = &db.hidden_projects;
         let mut db = db.db.lock().await;
         let db = &mut *db;
         let items: Vec<i32> = self
-            .build()
+            .build(hidden_projects)
             .offset(self.offset as i64)
             .limit((self.limit as i64).min(100))
             .load(db)?;
@@ -857,13 +1537,192 @@ impl PostQuery {
     }
 
     pub async fn count(&self, db: &Database) -> QueryResult<u64> {
+        let hidden_projects = &db.hidden_projects;
         let mut db = db.db.lock().await;
         let db = &mut *db;
-        let count: i64 = self.build().count().get_result(db)?;
+        let count: i64 = self.build(hidden_projects).count().get_result(db)?;
         Ok(count as u64)
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectSort {
+    /// Most posts first
+    #[default]
+    Posts,
+    Handle,
+    /// Most recently active first
+    Recent,
+    /// Earliest first post first
+    Oldest,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProjectQuery {
+    /// Only projects whose handle contains this
+    pub search: String,
+    /// Only projects followed by this project
+    pub followed_by: Option<u64>,
+    /// Only projects following this project
+    pub following: Option<u64>,
+    pub sort: ProjectSort,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectListing {
+    pub id: u64,
+    pub handle: String,
+    pub is_private: bool,
+    pub requires_logged_in: bool,
+    pub post_count: u64,
+    pub first_post: Option<String>,
+    pub last_post: Option<String>,
+}
+
+impl ProjectQuery {
+    pub async fn get(&self, db: &Database) -> QueryResult<Vec<ProjectListing>> {
+        use crate::schema::follows::dsl as follows;
+        use crate::schema::posts::dsl as posts;
+        use crate::schema::projects::dsl as projects;
+        use diesel::dsl::{count, max, min};
+
+        let hidden_projects = &db.hidden_projects;
+        let mut db = db.db.lock().await;
+        let db = &mut *db;
+
+        let post_count = count(posts::id.nullable());
+        let first_post = min(posts::published_at.nullable());
+        let last_post = max(posts::published_at.nullable());
+
+        let mut query = projects::projects
+            .left_join(posts::posts)
+            .group_by(projects::id)
+            .select((
+                projects::id,
+                projects::handle,
+                projects::is_private,
+                projects::requires_logged_in,
+                post_count,
+                first_post,
+                last_post,
+            ))
+            .filter(projects::id.ne_all(hidden_projects))
+            .into_boxed();
+
+        if !self.search.is_empty() {
+            query = query.filter(
+                projects::handle
+                    .like(Database::like_contains(&self.search))
+                    .escape('\\'),
+            );
+        }
+        if let Some(project) = self.followed_by {
+            let followed = follows::follows
+                .filter(follows::from_project_id.eq(project as i32))
+                .select(follows::to_project_id);
+            query = query.filter(projects::id.eq_any(followed));
+        }
+        if let Some(project) = self.following {
+            let followers = follows::follows
+                .filter(follows::to_project_id.eq(project as i32))
+                .select(follows::from_project_id);
+            query = query.filter(projects::id.eq_any(followers));
+        }
+
+        query = match self.sort {
+            ProjectSort::Posts => query.order_by((post_count.desc(), projects::handle.asc())),
+            ProjectSort::Handle => query.order_by(projects::handle.asc()),
+            ProjectSort::Recent => query.order_by((last_post.desc(), projects::handle.asc())),
+            ProjectSort::Oldest => query.order_by((first_post.is_null(), first_post.asc())),
+        };
+
+        let items: Vec<(i32, String, bool, bool, i64, Option<String>, Option<String>)> = query
+            .offset(self.offset as i64)
+            .limit((self.limit as i64).min(200))
+            .load(db)?;
+
+        Ok(items
+            .into_iter()
+            .map(
+                |(
+                    id,
+                    handle,
+                    is_private,
+                    requires_logged_in,
+                    post_count,
+                    first_post,
+                    last_post,
+                )| {
+                    ProjectListing {
+                        id: id as u64,
+                        handle,
+                        is_private,
+                        requires_logged_in,
+                        post_count: post_count as u64,
+                        first_post,
+                        last_post,
+                    }
+                },
+            )
+            .collect())
+    }
+
+    pub async fn count(&self, db: &Database) -> QueryResult<u64> {
+        use crate::schema::follows::dsl as follows;
+        use crate::schema::projects::dsl as projects;
+
+        let hidden_projects = &db.hidden_projects;
+        let mut db = db.db.lock().await;
+        let db = &mut *db;
+
+        let mut query = projects::projects
+            .filter(projects::id.ne_all(hidden_projects))
+            .into_boxed();
+
+        if !self.search.is_empty() {
+            query = query.filter(
+                projects::handle
+                    .like(Database::like_contains(&self.search))
+                    .escape('\\'),
+            );
+        }
+        if let Some(project) = self.followed_by {
+            let followed = follows::follows
+                .filter(follows::from_project_id.eq(project as i32))
+                .select(follows::to_project_id);
+            query = query.filter(projects::id.eq_any(followed));
+        }
+        if let Some(project) = self.following {
+            let followers = follows::follows
+                .filter(follows::to_project_id.eq(project as i32))
+                .select(follows::from_project_id);
+            query = query.filter(projects::id.eq_any(followers));
+        }
+
+        let count: i64 = query.count().get_result(db)?;
+        Ok(count as u64)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagUsagePost {
+    pub id: u64,
+    pub handle: String,
+    pub published_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagUsage {
+    pub post_count: u64,
+    pub first_post: Option<TagUsagePost>,
+    pub last_post: Option<TagUsagePost>,
+    /// (handle, number of posts), most posts first
+    pub top_posters: Vec<(String, u64)>,
+}
+
 /// Tag queries
 impl Database {
     pub async fn canonical_tag_capitalization(&self, the_tag: &str) -> QueryResult<Option<String>> {
@@ -881,7 +1740,171 @@ impl Database {
             .first(db)
             .optional()?;
 
-        Ok(res)
+        Ok(res)
+    }
+
+    /// Returns tags with the number of posts using them, most used first.
+    /// If `search` is not empty, only tags containing it are included.
+    pub async fn tag_counts(
+        &self,
+        project: Option<u64>,
+        search: &str,
+        offset: u64,
+        limit: u64,
+    ) -> QueryResult<Vec<(String, u64)>> {
+        use crate::schema::post_tags::dsl::*;
+        use crate::schema::posts::dsl as posts;
+        use diesel::dsl::count_star;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let mut query = post_tags
+            .group_by(tag)
+            .select((tag, count_star()))
+            .order_by((count_star().desc(), tag.asc()))
+            .offset(offset as i64)
+            .limit(limit as i64)
+            .into_boxed();
+
+        if !self.hidden_projects.is_empty() {
+            let hidden_posts = posts::posts
+                .filter(posts::posting_project_id.eq_any(&self.hidden_projects))
+                .select(posts::id);
+            query = query.filter(post_id.ne_all(hidden_posts));
+        }
+        if let Some(project) = project {
+            let project_posts = posts::posts
+                .filter(posts::posting_project_id.eq(project as i32))
+                .select(posts::id);
+            query = query.filter(post_id.eq_any(project_posts));
+        }
+        if !search.is_empty() {
+            query = query.filter(tag.like(Self::like_contains(search)).escape('\\'));
+        }
+
+        let counts: Vec<(String, i64)> = query.load(db)?;
+        Ok(counts
+            .into_iter()
+            .map(|(the_tag, count)| (the_tag, count as u64))
+            .collect())
+    }
+
+    /// Returns the number of different tags, with the same filters as [Self::tag_counts].
+    pub async fn distinct_tag_count(&self, project: Option<u64>, search: &str) -> QueryResult<u64> {
+        use crate::schema::post_tags::dsl::*;
+        use crate::schema::posts::dsl as posts;
+        use diesel::dsl::sql;
+        use diesel::sql_types::BigInt;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let mut query = post_tags
+            .select(sql::<BigInt>("count(distinct tag)"))
+            .into_boxed();
+
+        if !self.hidden_projects.is_empty() {
+            let hidden_posts = posts::posts
+                .filter(posts::posting_project_id.eq_any(&self.hidden_projects))
+                .select(posts::id);
+            query = query.filter(post_id.ne_all(hidden_posts));
+        }
+        if let Some(project) = project {
+            let project_posts = posts::posts
+                .filter(posts::posting_project_id.eq(project as i32))
+                .select(posts::id);
+            query = query.filter(post_id.eq_any(project_posts));
+        }
+        if !search.is_empty() {
+            query = query.filter(tag.like(Self::like_contains(search)).escape('\\'));
+        }
+
+        let count: i64 = query.get_result(db)?;
+        Ok(count as u64)
+    }
+
+    /// LIKE pattern for text containing `s`, with `\` as the escape character.
+    fn like_contains(s: &str) -> String {
+        let escaped = s
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("%{escaped}%")
+    }
+
+    /// Returns statistics about posts with any of these tags.
+    pub async fn tag_usage(&self, tags: &[String]) -> QueryResult<TagUsage> {
+        use crate::schema::post_tags::dsl as post_tags;
+        use crate::schema::posts::dsl as posts;
+        use crate::schema::projects::dsl as projects;
+        use diesel::dsl::count_star;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let tagged_posts = post_tags::post_tags
+            .filter(post_tags::tag.eq_any(tags))
+            .select(post_tags::post_id);
+
+        let post_count: i64 = posts::posts
+            .filter(posts::id.eq_any(tagged_posts.clone()))
+            .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+            .count()
+            .get_result(db)?;
+
+        let usage_post = |first: bool, db: &mut SqliteConnection| {
+            let query = posts::posts
+                .inner_join(projects::projects)
+                .filter(posts::id.eq_any(tagged_posts.clone()))
+                .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+                .filter(posts::published_at.is_not_null())
+                .select((
+                    posts::id,
+                    projects::handle,
+                    posts::published_at.assume_not_null(),
+                ));
+
+            let post: Option<(i32, String, String)> = if first {
+                query
+                    .order_by(posts::published_at.asc())
+                    .first(db)
+                    .optional()?
+            } else {
+                query
+                    .order_by(posts::published_at.desc())
+                    .first(db)
+                    .optional()?
+            };
+
+            QueryResult::Ok(post.map(|(id, handle, published_at)| TagUsagePost {
+                id: id as u64,
+                handle,
+                published_at,
+            }))
+        };
+        let first_post = usage_post(true, db)?;
+        let last_post = usage_post(false, db)?;
+
+        let top_posters: Vec<(String, i64)> = posts::posts
+            .inner_join(projects::projects)
+            .filter(posts::id.eq_any(tagged_posts))
+            .filter(posts::posting_project_id.ne_all(&self.hidden_projects))
+            .group_by(projects::handle)
+            .select((projects::handle, count_star()))
+            .order_by((count_star().desc(), projects::handle.asc()))
+            .limit(10)
+            .load(db)?;
+
+        Ok(TagUsage {
+            post_count: post_count as u64,
+            first_post,
+            last_post,
+            top_posters: top_posters
+                .into_iter()
+                .map(|(handle, count)| (handle, count as u64))
+                .collect(),
+        })
     }
 
     pub async fn synonym_tags(&self, tag: &str) -> QueryResult<Vec<String>> {
@@ -1161,6 +2184,119 @@ impl Database {
             .load(db)
     }
 
+    pub async fn get_resource_validators(
+        &self,
+        the_url: &Url,
+    ) -> QueryResult<Option<DbResourceValidators>> {
+        use crate::schema::resource_validators::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        resource_validators
+            .filter(url.eq(the_url.to_string()))
+            .first::<DbResourceValidators>(db)
+            .optional()
+    }
+
+    /// Returns true if loading the resource failed before in a way that won't change by retrying.
+    pub async fn is_resource_failure_skipped(&self, the_url: &Url) -> QueryResult<bool> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let result: Option<bool> = resource_failures
+            .filter(url.eq(the_url.to_string()))
+            .select(skip)
+            .first(db)
+            .optional()?;
+        Ok(result.unwrap_or(false))
+    }
+
+    /// Returns how often loading the resource failed so far.
+    pub async fn resource_failure_attempts(&self, the_url: &Url) -> QueryResult<usize> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let result: Option<i32> = resource_failures
+            .filter(url.eq(the_url.to_string()))
+            .select(attempts)
+            .first(db)
+            .optional()?;
+        Ok(result.unwrap_or(0) as usize)
+    }
+
+    /// Returns all failures, most recent first.
+    pub async fn get_resource_failures(&self) -> QueryResult<Vec<DbResourceFailure>> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        resource_failures
+            .order_by(last_failed_at.desc())
+            .load::<DbResourceFailure>(db)
+    }
+
+    /// Returns references for all URLs in the failure list.
+    pub async fn get_resource_failure_references(
+        &self,
+    ) -> QueryResult<HashMap<String, ResourceReferences>> {
+        use crate::schema::comment_resources::dsl as comment_resources;
+        use crate::schema::comments::dsl as comments;
+        use crate::schema::post_resources::dsl as post_resources;
+        use crate::schema::posts::dsl as posts;
+        use crate::schema::project_resources::dsl as project_resources;
+        use crate::schema::projects::dsl as projects;
+        use crate::schema::resource_failures::dsl as failures;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let failed_urls = failures::resource_failures.select(failures::url);
+        let mut refs = HashMap::<String, ResourceReferences>::new();
+
+        let post_refs: Vec<(String, i32, String)> = post_resources::post_resources
+            .inner_join(posts::posts.inner_join(projects::projects))
+            .filter(post_resources::url.eq_any(failed_urls))
+            .select((post_resources::url, posts::id, projects::handle))
+            .load(db)?;
+        for (the_url, post, handle) in post_refs {
+            let refs = refs.entry(the_url).or_default();
+            refs.posts.push((post as u64, handle));
+        }
+
+        let project_refs: Vec<(String, i32, String)> = project_resources::project_resources
+            .inner_join(projects::projects)
+            .filter(project_resources::url.eq_any(failed_urls))
+            .select((project_resources::url, projects::id, projects::handle))
+            .load(db)?;
+        for (the_url, project, handle) in project_refs {
+            let refs = refs.entry(the_url).or_default();
+            refs.projects.push((project as u64, handle));
+        }
+
+        let comment_refs: Vec<(String, String, i32, String)> = comment_resources::comment_resources
+            .inner_join(comments::comments.inner_join(posts::posts.inner_join(projects::projects)))
+            .filter(comment_resources::url.eq_any(failed_urls))
+            .select((
+                comment_resources::url,
+                comments::id,
+                posts::id,
+                projects::handle,
+            ))
+            .load(db)?;
+        for (the_url, comment, post, handle) in comment_refs {
+            let refs = refs.entry(the_url).or_default();
+            refs.comments.push((comment, post as u64, handle));
+        }
+
+        Ok(refs)
+    }
+
     pub async fn total_url_file_count(&self) -> QueryResult<u64> {
         use crate::schema::url_files::dsl::*;
 
@@ -1330,6 +2466,21 @@ impl Database {
             post.share_of_post_id
         );
 
+        // posts in a share tree are always inserted so that the share stays intact
+        if !is_share_post && !ctx.post_filter.accepts(post) {
+            trace!("insert_post {}: skipped by post filter", post.post_id);
+            return Ok(());
+        }
+
+        // posts loaded while logged out are missing content, so they shouldn't replace a copy
+        // that was loaded while logged in (along with its share tree and projects)
+        if post.limited_visibility_reason == LimitedVisibilityReason::LogInFirst
+            && self.has_post(post.post_id).await?
+        {
+            trace!("insert_post {}: keeping stored copy", post.post_id);
+            return Ok(());
+        }
+
         for (i, share_post) in post.share_tree.iter().enumerate() {
             let prev_post = i.checked_sub(1).and_then(|i| post.share_tree.get(i));
 
@@ -1386,7 +2537,14 @@ impl Database {
                     match single_post {
                         Ok(single_post) => {
                             return self
-                                .insert_single_post(ctx, state, login, &single_post, add_only)
+                                .insert_single_post_impl(
+                                    ctx,
+                                    state,
+                                    login,
+                                    &single_post,
+                                    true,
+                                    add_only,
+                                )
                                 .await;
                         }
                         Err(err @ GetError::NotFound(..)) => {
@@ -1536,6 +2694,21 @@ impl Database {
                 .context("DB:post_tags")?;
         }
 
+        {
+            use crate::schema::asks::dsl::*;
+            diesel::delete(asks)
+                .filter(post_id.eq(post.post_id as i32))
+                .execute(db)
+                .context("DB:asks clear")?;
+
+            diesel::insert_or_ignore_into(asks)
+                .values(&DbAsk::from_blocks(post.post_id, &post.blocks))
+                .execute(db)
+                .context("DB:asks")?;
+        }
+
+        Self::insert_post_links(db, post.post_id, &post.blocks).context("DB:post_links")?;
+
         {
             use crate::schema::post_resources::dsl::*;
             diesel::delete(post_resources)
@@ -1557,6 +2730,58 @@ impl Database {
         Ok(())
     }
 
+    /// Replaces the mentions and links to other posts stored for a post.
+    fn insert_post_links(
+        db: &mut SqliteConnection,
+        post: u64,
+        blocks: &[PostBlock],
+    ) -> QueryResult<()> {
+        use crate::schema::mentions::dsl as mentions;
+        use crate::schema::post_links::dsl as post_links;
+
+        let links = blocks.collect_links();
+
+        diesel::delete(mentions::mentions)
+            .filter(mentions::post_id.eq(post as i32))
+            .execute(db)?;
+        diesel::insert_or_ignore_into(mentions::mentions)
+            .values(
+                &links
+                    .mentions
+                    .iter()
+                    .map(|handle| {
+                        (
+                            mentions::post_id.eq(post as i32),
+                            mentions::handle.eq(handle),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(db)?;
+
+        diesel::delete(post_links::post_links)
+            .filter(post_links::post_id.eq(post as i32))
+            .execute(db)?;
+        diesel::insert_or_ignore_into(post_links::post_links)
+            .values(
+                &links
+                    .posts
+                    .iter()
+                    .filter(|(_, linked)| *linked != post)
+                    .map(|(handle, linked)| {
+                        (
+                            post_links::post_id.eq(post as i32),
+                            post_links::linked_post_id.eq(*linked as i32),
+                            post_links::linked_handle.eq(handle),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(db)?;
+
+        Ok(())
+    }
+
     pub async fn insert_comment(
         &self,
         on_post_id: u64,
@@ -1658,19 +2883,46 @@ impl Database {
         login: &LoginLoggedIn,
         single_post: &SinglePost,
         add_only: bool,
+    ) -> anyhow::Result<()> {
+        self.insert_single_post_impl(ctx, state, login, single_post, false, add_only)
+            .await
+    }
+
+    #[async_recursion::async_recursion]
+    async fn insert_single_post_impl(
+        &self,
+        ctx: &CohostContext,
+        state: &Mutex<CurrentStateV1>,
+        login: &LoginLoggedIn,
+        single_post: &SinglePost,
+        is_share_post: bool,
+        add_only: bool,
     ) -> anyhow::Result<()> {
         trace!("insert_single_post {}", single_post.post.post_id);
 
-        self.insert_post(ctx, state, login, &single_post.post, false, None, add_only)
-            .await
-            .with_context(|| {
-                format!(
-                    "inserting single post {}/{}",
-                    single_post.post.posting_project.handle, single_post.post.filename
-                )
-            })?;
+        self.insert_post(
+            ctx,
+            state,
+            login,
+            &single_post.post,
+            is_share_post,
+            None,
+            add_only,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "inserting single post {}/{}",
+                single_post.post.posting_project.handle, single_post.post.filename
+            )
+        })?;
 
         for (&post, comments) in &single_post.comments {
+            if !self.has_post(post).await? {
+                // skipped by the post filter
+                continue;
+            }
+
             for comment in comments {
                 self.insert_comment(post, comment, add_only)
                     .await
@@ -1773,6 +3025,137 @@ impl Database {
         Ok(())
     }
 
+    pub async fn insert_resource_validators(
+        &self,
+        the_url: &Url,
+        the_etag: Option<&str>,
+        the_last_modified: Option<&str>,
+        the_size: u64,
+        the_sha256: &str,
+    ) -> anyhow::Result<()> {
+        use crate::schema::resource_validators::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let row = DbResourceValidators {
+            url: the_url.to_string(),
+            etag: the_etag.map(|s| s.to_string()),
+            last_modified: the_last_modified.map(|s| s.to_string()),
+            size: the_size as i64,
+            sha256: the_sha256.to_string(),
+            checked_at: Utc::now().to_rfc3339(),
+        };
+
+        diesel::insert_into(resource_validators)
+            .values(&row)
+            .on_conflict(url)
+            .do_update()
+            .set(&row)
+            .execute(db)?;
+
+        Ok(())
+    }
+
+    /// Marks the resource as checked just now, without changing anything else.
+    pub async fn touch_resource_validators(&self, the_url: &Url) -> anyhow::Result<()> {
+        use crate::schema::resource_validators::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        diesel::update(resource_validators)
+            .filter(url.eq(the_url.to_string()))
+            .set(checked_at.eq(Utc::now().to_rfc3339()))
+            .execute(db)?;
+
+        Ok(())
+    }
+
+    /// Remembers an old version of a resource that was replaced by a new download.
+    /// Path must be stripped!
+    pub async fn insert_resource_version(
+        &self,
+        the_url: &Url,
+        path: &Path,
+        the_sha256: Option<&str>,
+    ) -> anyhow::Result<()> {
+        use crate::schema::resource_versions::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let path = path.to_str().context("path contains invalid UTF-8")?;
+
+        diesel::insert_into(resource_versions)
+            .values(&(
+                url.eq(the_url.to_string()),
+                file_path.eq(path),
+                sha256.eq(the_sha256),
+                replaced_at.eq(Utc::now().to_rfc3339()),
+            ))
+            .execute(db)?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt at loading a resource.
+    /// If `skip` is set, the resource won't be loaded again until the failure is removed.
+    pub async fn insert_resource_failure(
+        &self,
+        the_url: &Url,
+        the_error_class: &str,
+        the_status_code: Option<u16>,
+        the_message: &str,
+        the_skip: bool,
+    ) -> anyhow::Result<()> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        let now = Utc::now().to_rfc3339();
+        let the_status_code = the_status_code.map(|code| code as i32);
+
+        diesel::insert_into(resource_failures)
+            .values(&(
+                url.eq(the_url.to_string()),
+                error_class.eq(the_error_class),
+                status_code.eq(the_status_code),
+                message.eq(the_message),
+                attempts.eq(1),
+                first_failed_at.eq(&now),
+                last_failed_at.eq(&now),
+                skip.eq(the_skip),
+            ))
+            .on_conflict(url)
+            .do_update()
+            .set((
+                error_class.eq(the_error_class),
+                status_code.eq(the_status_code),
+                message.eq(the_message),
+                attempts.eq(attempts + 1),
+                last_failed_at.eq(&now),
+                skip.eq(the_skip),
+            ))
+            .execute(db)?;
+
+        Ok(())
+    }
+
+    pub async fn remove_resource_failure(&self, the_url: &str) -> anyhow::Result<()> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let mut db = self.db.lock().await;
+        let db = &mut *db;
+
+        diesel::delete(resource_failures)
+            .filter(url.eq(the_url))
+            .execute(db)?;
+
+        Ok(())
+    }
+
     pub async fn remove_url_file(&self, orig_url: &Url) -> anyhow::Result<()> {
         use crate::schema::url_files::dsl::*;
 
@@ -1872,6 +3255,134 @@ impl Database {
         Ok(())
     }
 
+    /// Removes the failures of resources that were rejected by resource rules, so they're checked
+    /// against the current rules again.
+    pub fn remove_rule_failures(db: &mut SqliteConnection) -> anyhow::Result<()> {
+        use crate::schema::resource_failures::dsl::*;
+
+        let count = diesel::delete(resource_failures)
+            .filter(error_class.eq("Rule"))
+            .execute(db)?;
+        if count > 0 {
+            info!("Resource rules changed, will check {count} rejected resources again");
+        }
+
+        Ok(())
+    }
+
+    /// Moves failed URLs from the old downloader state into the failure list.
+    /// Nothing is known about them anymore, except that they shouldn't be loaded again.
+    pub fn migrate_old_failed_urls(
+        db: &mut SqliteConnection,
+        failed_urls: &[String],
+    ) -> anyhow::Result<()> {
+        use crate::schema::resource_failures::dsl::*;
+
+        if failed_urls.is_empty() {
+            return Ok(());
+        }
+
+        info!("Moving {} failed URLs to the database", failed_urls.len());
+
+        let now = Utc::now().to_rfc3339();
+        db.transaction(|db| {
+            for the_url in failed_urls {
+                diesel::insert_into(resource_failures)
+                    .values(&(
+                        url.eq(the_url),
+                        error_class.eq("Unknown"),
+                        status_code.eq(None::<i32>),
+                        message.eq("failed in an earlier version of cohost-dl"),
+                        attempts.eq(1),
+                        first_failed_at.eq(&now),
+                        last_failed_at.eq(&now),
+                        skip.eq(true),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(db)?;
+            }
+            QueryResult::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Fills the asks table from posts that were archived before it existed.
+    pub fn migrate_asks(db: &mut SqliteConnection) -> anyhow::Result<()> {
+        use crate::schema::asks::dsl as asks;
+        use crate::schema::posts::dsl as posts;
+
+        if Self::get_migration_state(db, "asks")?.as_deref() == Some("1") {
+            return Ok(());
+        }
+
+        for i in (0..).map(|i| i * 1000) {
+            let posts: Vec<DbPost> = posts::posts
+                .filter(posts::response_to_ask_id.is_not_null())
+                .order_by(posts::id)
+                .offset(i)
+                .limit(1000)
+                .load(db)?;
+
+            if posts.is_empty() {
+                break;
+            }
+
+            if i == 0 {
+                info!("Indexing asks");
+            }
+
+            for post in posts {
+                let data = post.data()?;
+
+                diesel::insert_or_ignore_into(asks::asks)
+                    .values(&DbAsk::from_blocks(post.id as u64, &data.blocks))
+                    .execute(db)?;
+            }
+        }
+
+        Self::set_migration_state(db, "asks", "1")?;
+
+        Ok(())
+    }
+
+    /// Fills the mentions and post_links tables from posts that were archived before they existed.
+    pub fn migrate_post_links(db: &mut SqliteConnection) -> anyhow::Result<()> {
+        use crate::schema::posts::dsl as posts;
+
+        if Self::get_migration_state(db, "post_links")?.as_deref() == Some("1") {
+            return Ok(());
+        }
+
+        for i in (0..).map(|i| i * 1000) {
+            let posts: Vec<DbPost> = posts::posts
+                .order_by(posts::id)
+                .offset(i)
+                .limit(1000)
+                .load(db)?;
+
+            if posts.is_empty() {
+                break;
+            }
+
+            if i == 0 {
+                info!("Indexing mentions and post links");
+            }
+
+            db.transaction(|db| {
+                for post in &posts {
+                    let data = post.data()?;
+                    Self::insert_post_links(db, post.id as u64, &data.blocks)?;
+                }
+                anyhow::Ok(())
+            })?;
+        }
+
+        Self::set_migration_state(db, "post_links", "1")?;
+
+        Ok(())
+    }
+
     fn migrate_posts_v2(db: &mut SqliteConnection) -> anyhow::Result<()> {
         use crate::schema::posts::dsl as posts;
 
@@ -1,6 +1,12 @@
 use crate::bundled_files::COHOST_STATIC;
 use crate::comment::Permission;
-use crate::context::{CohostContext, GetError, MAX_RETRIES};
+use crate::context::{CohostContext, GetError, Revalidation, MAX_RETRIES};
+use crate::data::Database;
+use crate::failures::{
+    group_failures, load_failures, print_failure_groups, FailureFilter, FailureGrouping,
+};
+use crate::post_filter::PostFilter;
+use crate::resource_rules::ResourceRules;
 use crate::trpc::LoginLoggedIn;
 use crate::Config;
 use anyhow::{bail, Context};
@@ -28,11 +34,20 @@ pub struct CurrentStateV1 {
     pub has_likes: HashSet<u64>,
     pub has_follows: HashSet<u64>,
     pub projects: HashMap<u64, ProjectState>,
+    /// Only read from older state files. Failures are now stored in the database.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub failed_urls: Vec<String>,
     #[serde(default)]
     pub tagged_posts: HashMap<String, TaggedPostsState>,
     #[serde(default)]
     pub comments_lost_to_time: HashSet<u64>,
+    /// The page that was active before switching to other pages to load their likes and follows.
+    /// This is only set until it has been switched back.
+    #[serde(default)]
+    pub switched_from_project: Option<u64>,
+    /// Hash of the resource rules that rejected resources were checked against.
+    #[serde(default)]
+    pub resource_rules_hash: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -154,7 +169,7 @@ async fn load_likes(
 
     info!("loading liked posts for project {}", login.project_id);
 
-    let bar = ProgressBar::new_spinner();
+    let bar = ctx.progress.add(ProgressBar::new_spinner());
     bar.enable_steady_tick(Duration::from_millis(100));
 
     let mut page = 0;
@@ -185,6 +200,118 @@ async fn load_likes(
     Ok(())
 }
 
+/// Switches the active page, remembering which one was active in the beginning.
+async fn switch_project(
+    ctx: &CohostContext,
+    state: &Mutex<CurrentStateV1>,
+    from_project: u64,
+    to_project: u64,
+) -> anyhow::Result<LoginLoggedIn> {
+    {
+        let mut state = state.lock().await;
+        if state.switched_from_project.is_none() {
+            state.switched_from_project = Some(from_project);
+            state.store_state()?;
+        }
+    }
+
+    ctx.projects_switch_project(to_project).await?;
+
+    let login = ctx.login_logged_in().await?;
+    if login.project_id != to_project {
+        bail!(
+            "switched active page to {to_project}, but the active page is now {}",
+            login.project_id
+        );
+    }
+
+    Ok(login)
+}
+
+/// Switches back to the page that was active before switching to other pages, if needed.
+/// This also happens if the last run quit before it could switch back.
+async fn restore_active_project(
+    ctx: &CohostContext,
+    state: &Mutex<CurrentStateV1>,
+    login: LoginLoggedIn,
+) -> anyhow::Result<LoginLoggedIn> {
+    let Some(project_id) = state.lock().await.switched_from_project else {
+        return Ok(login);
+    };
+
+    let login = if login.project_id != project_id {
+        info!("switching back to the original active page ({project_id})");
+        ctx.projects_switch_project(project_id).await?;
+
+        let login = ctx.login_logged_in().await?;
+        if login.project_id != project_id {
+            bail!(
+                "could not switch back to the original active page: the active page is now {}",
+                login.project_id
+            );
+        }
+        login
+    } else {
+        login
+    };
+
+    let mut state = state.lock().await;
+    state.switched_from_project = None;
+    state.store_state()?;
+
+    Ok(login)
+}
+
+/// Loads follows and (if configured) likes for the active page,
+/// and also for all other pages the account can edit if `load_all_pages` is set.
+async fn load_all_follows_and_likes(
+    ctx: &CohostContext,
+    config: &Config,
+    state: &Mutex<CurrentStateV1>,
+    login: LoginLoggedIn,
+) -> anyhow::Result<LoginLoggedIn> {
+    let projects = if config.load_all_pages {
+        let projects = ctx.projects_list_edited_projects().await?;
+        let mut projects: Vec<_> = projects.projects.iter().map(|p| p.project_id).collect();
+        // start with the current page to avoid switching more than necessary
+        projects.sort_by_key(|id| *id != login.project_id);
+        projects
+    } else {
+        vec![login.project_id]
+    };
+
+    let original_project = login.project_id;
+    let mut login = login;
+
+    for project_id in projects {
+        let needs_follows = !state.lock().await.has_follows.contains(&project_id);
+        let needs_likes = config.load_likes && !state.lock().await.has_likes.contains(&project_id);
+        if !needs_follows && !needs_likes {
+            continue;
+        }
+
+        if login.project_id != project_id {
+            login = switch_project(ctx, state, original_project, project_id)
+                .await
+                .with_context(|| format!("switching active page to {project_id}"))?;
+        }
+
+        if needs_follows {
+            load_follows(ctx, &login, &mut *state.lock().await)
+                .await
+                .context("loading follows")?;
+            let _ = state.lock().await.store_state();
+        }
+        if needs_likes {
+            load_likes(ctx, &login, state)
+                .await
+                .context("loading likes")?;
+        }
+    }
+
+    restore_active_project(ctx, state, login).await
+}
+
 async fn get_profile_inaccessible_reason(
     ctx: &CohostContext,
     handle: &str,
@@ -214,7 +341,8 @@ async fn load_profile_posts(
     project_id: u64,
     new_only: bool,
     ignore_inaccessible: bool,
-) -> anyhow::Result<()> {
+    max_posts: Option<u64>,
+) -> anyhow::Result<u64> {
     let project = ctx.project(project_id).await?;
 
     if let Some(reason) = get_profile_inaccessible_reason(ctx, &project.handle).await? {
@@ -223,7 +351,7 @@ async fn load_profile_posts(
                 "\x1b[31;1m\n\ncannot load profile posts for @{}: {}\n\n\x1b[m",
                 project.handle, reason
             );
-            return Ok(());
+            return Ok(0);
         } else {
             bail!(
                 "cannot load profile posts for @{}: {}",
@@ -239,11 +367,13 @@ async fn load_profile_posts(
         info!("loading all posts from @{}", project.handle);
     }
 
-    let bar = ProgressBar::new_spinner();
+    let bar = ctx.progress.add(ProgressBar::new_spinner());
     bar.enable_steady_tick(Duration::from_millis(100));
     bar.set_message(format!("@{} first page", project.handle));
 
     let mut count = 0;
+    let mut reached_limit = false;
+    let mut reached_date = false;
     'outer: for page in 0.. {
         let posts = ctx.posts_profile_posts(&project.handle, page).await?;
 
@@ -263,6 +393,16 @@ async fn load_profile_posts(
                 break 'outer;
             }
 
+            if max_posts.map_or(false, |max| count >= max) {
+                reached_limit = true;
+                break 'outer;
+            }
+
+            if ctx.post_filter.is_too_old(post) {
+                reached_date = true;
+                break 'outer;
+            }
+
             bar.set_message(format!(
                 "{message} ← adding post {i}/{} (ID {})",
                 posts.posts.len(),
@@ -281,6 +421,21 @@ async fn load_profile_posts(
 
     bar.finish_and_clear();
 
+    if reached_limit {
+        info!(
+            "loaded posts from @{}: {count} (stopped at the post limit)",
+            project.handle
+        );
+        return Ok(count);
+    }
+    if reached_date {
+        info!(
+            "loaded posts from @{}: {count} (stopped at published_after)",
+            project.handle
+        );
+        return Ok(count);
+    }
+
     info!("loaded all posts from @{}: {count}", project.handle);
     state
         .lock()
@@ -290,6 +445,102 @@ async fn load_profile_posts(
         .unwrap()
         .has_all_posts = true;
 
+    Ok(count)
+}
+
+/// Crawls the neighbourhood of followed pages, up to the configured depth.
+///
+/// Cohost doesn't expose the follow lists of other pages, only those of our own pages. So only
+/// the first step follows actual follows; beyond that, this crawls the share graph instead: a
+/// page's neighbours are the pages it has shared posts from, which are only known once its posts
+/// are loaded. Nothing found this way is stored as a follow.
+async fn crawl_neighbourhood(
+    ctx: &CohostContext,
+    config: &Config,
+    state: &Mutex<CurrentStateV1>,
+    login: &LoginLoggedIn,
+) -> anyhow::Result<()> {
+    let mut visited: HashSet<u64> = state.lock().await.has_follows.iter().copied().collect();
+    let mut frontier = ctx.followed_by_any().await?;
+
+    let mut post_count = 0;
+    let mut project_count = 0;
+
+    info!(
+        "crawling the neighbourhood of followed pages (depth {})",
+        config.follow_crawl_depth
+    );
+
+    'outer: for depth in 1..=config.follow_crawl_depth {
+        let mut next_frontier = Vec::new();
+
+        for project_id in frontier {
+            if !visited.insert(project_id) {
+                continue;
+            }
+            if project_count >= config.follow_crawl_max_projects {
+                info!(
+                    "stopping crawl at the page limit ({})",
+                    config.follow_crawl_max_projects
+                );
+                break 'outer;
+            }
+
+            let handle = match ctx.project(project_id).await {
+                Ok(project) => project.handle,
+                Err(e) => {
+                    warn!("skipping page {project_id} in crawl: {e}");
+                    continue;
+                }
+            };
+            if config.skip_follows.contains(&handle) {
+                continue;
+            }
+            project_count += 1;
+
+            let remaining_posts = config.follow_crawl_max_posts.saturating_sub(post_count);
+            if config.follow_crawl_load_posts && remaining_posts > 0 {
+                let has_all_posts = state
+                    .lock()
+                    .await
+                    .projects
+                    .entry(project_id)
+                    .or_default()
+                    .has_all_posts;
+
+                if !has_all_posts {
+                    match load_profile_posts(
+                        ctx,
+                        state,
+                        login,
+                        project_id,
+                        false,
+                        true,
+                        Some(remaining_posts),
+                    )
+                    .await
+                    {
+                        Ok(count) => post_count += count,
+                        Err(e) => warn!("could not load posts from @{handle} in crawl: {e:?}"),
+                    }
+
+                    ctx.db.vacuum().await?;
+                }
+            }
+
+            if depth < config.follow_crawl_depth {
+                match ctx.projects_shared_from(project_id).await {
+                    Ok(projects) => next_frontier.extend(projects),
+                    Err(e) => warn!("could not find pages shared by @{handle}: {e}"),
+                }
+            }
+        }
+
+        frontier = next_frontier;
+    }
+
+    info!("crawled {project_count} pages and loaded {post_count} posts");
+
     Ok(())
 }
 
@@ -301,7 +552,7 @@ async fn load_tagged_posts(
 ) -> anyhow::Result<()> {
     info!("loading all posts tagged with #{tag}");
 
-    let bar = ProgressBar::new_spinner();
+    let bar = ctx.progress.add(ProgressBar::new_spinner());
     bar.enable_steady_tick(Duration::from_millis(100));
     bar.set_message(format!("#{tag} first page"));
 
@@ -323,8 +574,9 @@ async fn load_tagged_posts(
     let mut canonical_tag = None;
 
     let mut count = 0;
+    let mut reached_date = false;
     let mut has_next = true;
-    while has_next {
+    while has_next && !reached_date {
         let feed = ctx
             .load_tagged_posts(tag, ref_timestamp, skip_posts)
             .await?;
@@ -371,6 +623,11 @@ async fn load_tagged_posts(
                 post.post_id
             ));
 
+            if ctx.post_filter.is_too_old(post) {
+                reached_date = true;
+                break;
+            }
+
             ctx.insert_post(ctx, state, login, post, false, None, false)
                 .await?;
         }
@@ -391,6 +648,11 @@ async fn load_tagged_posts(
 
     bar.finish_and_clear();
 
+    if reached_date {
+        info!("loaded posts tagged with #{tag}: {count} (stopped at published_after)");
+        return Ok(());
+    }
+
     info!("loaded all posts tagged with #{tag}: {count}");
     state
         .lock()
@@ -415,7 +677,7 @@ async fn load_specific_posts(
     login: &LoginLoggedIn,
     posts: &[String],
 ) -> anyhow::Result<()> {
-    let progress = ProgressBar::new(posts.len() as u64);
+    let progress = ctx.progress.add(ProgressBar::new(posts.len() as u64));
     progress.set_style(long_progress_style());
 
     progress.set_message("loading specific posts");
@@ -514,7 +776,7 @@ async fn posts_without_comments(
     let mut posts_without_comments = Vec::new();
 
     let total_count = ctx.total_non_transparent_post_count().await?;
-    let progress = ProgressBar::new(total_count);
+    let progress = ctx.progress.add(ProgressBar::new(total_count));
     progress.set_style(long_progress_style());
 
     progress.set_message("checking posts");
@@ -555,7 +817,7 @@ async fn load_comments_for_posts(
     login: &LoginLoggedIn,
     mut posts: Vec<u64>,
 ) -> anyhow::Result<()> {
-    let progress = ProgressBar::new(posts.len() as u64);
+    let progress = ctx.progress.add(ProgressBar::new(posts.len() as u64));
     progress.set_style(long_progress_style());
 
     posts.sort();
@@ -654,7 +916,9 @@ async fn fix_bad_transparent_shares(
         return Ok(());
     }
 
-    let progress = ProgressBar::new(bad_transparent_shares.len() as u64);
+    let progress = ctx
+        .progress
+        .add(ProgressBar::new(bad_transparent_shares.len() as u64));
     progress.set_style(long_progress_style());
 
     progress.set_message("fixing transparent shares");
@@ -715,7 +979,6 @@ async fn fix_bad_transparent_shares(
 
 async fn par_load_resources<T: Send + Sync>(
     ctx: &CohostContext,
-    state: &Mutex<CurrentStateV1>,
     loaded: &Arc<AtomicU64>,
     items: &[T],
     error_id: impl Fn(&T) -> String + Send + Copy,
@@ -751,7 +1014,7 @@ async fn par_load_resources<T: Send + Sync>(
                     tries += 1;
 
                     let res = ctx
-                        .load_resource_to_file(&url, &state, Some(&mut did_something))
+                        .load_resource_to_file(&url, Some(&mut did_something))
                         .await;
 
                     match res {
@@ -806,16 +1069,13 @@ async fn par_load_resources<T: Send + Sync>(
 
 const RESOURCE_LOAD_BATCH_SIZE: u64 = 5;
 
-async fn load_cohost_resources(
-    ctx: &CohostContext,
-    state: &Mutex<CurrentStateV1>,
-) -> anyhow::Result<()> {
+async fn load_cohost_resources(ctx: &CohostContext) -> anyhow::Result<()> {
     let files: Vec<_> = COHOST_STATIC
         .lines()
         .filter(|line| !line.is_empty())
         .collect();
 
-    let progress = ProgressBar::new(files.len() as u64);
+    let progress = ctx.progress.add(ProgressBar::new(files.len() as u64));
     progress.set_style(long_progress_style());
     progress.set_message("loading static files");
 
@@ -823,7 +1083,7 @@ async fn load_cohost_resources(
 
     for (i, chunk) in files.chunks(RESOURCE_LOAD_BATCH_SIZE as usize).enumerate() {
         progress.set_position(i as u64 * RESOURCE_LOAD_BATCH_SIZE);
-        par_load_resources(ctx, state, &loaded, chunk, |url| url.to_string(), |url| url).await?;
+        par_load_resources(ctx, &loaded, chunk, |url| url.to_string(), |url| url).await?;
     }
 
     progress.finish_and_clear();
@@ -838,15 +1098,12 @@ async fn load_cohost_resources(
     Ok(())
 }
 
-async fn load_post_resources(
-    ctx: &CohostContext,
-    state: &Mutex<CurrentStateV1>,
-) -> anyhow::Result<()> {
+async fn load_post_resources(ctx: &CohostContext) -> anyhow::Result<()> {
     let total = ctx.total_post_resources_count().await?;
 
     info!("checking post resource files");
 
-    let progress = ProgressBar::new(total);
+    let progress = ctx.progress.add(ProgressBar::new(total));
     progress.set_style(long_progress_style());
 
     let loaded = Arc::new(AtomicU64::new(0));
@@ -864,7 +1121,6 @@ async fn load_post_resources(
 
         par_load_resources(
             ctx,
-            state,
             &loaded,
             &items,
             |(post, _)| format!("post {post}"),
@@ -885,15 +1141,12 @@ async fn load_post_resources(
     Ok(())
 }
 
-async fn load_project_resources(
-    ctx: &CohostContext,
-    state: &Mutex<CurrentStateV1>,
-) -> anyhow::Result<()> {
+async fn load_project_resources(ctx: &CohostContext) -> anyhow::Result<()> {
     let total = ctx.total_project_resources_count().await?;
 
     info!("checking project resource files");
 
-    let progress = ProgressBar::new(total);
+    let progress = ctx.progress.add(ProgressBar::new(total));
     progress.set_style(long_progress_style());
 
     let loaded = Arc::new(AtomicU64::new(0));
@@ -911,7 +1164,6 @@ async fn load_project_resources(
 
         par_load_resources(
             ctx,
-            state,
             &loaded,
             &items,
             |(project, _)| format!("project {project}"),
@@ -932,15 +1184,12 @@ async fn load_project_resources(
     Ok(())
 }
 
-async fn load_comment_resources(
-    ctx: &CohostContext,
-    state: &Mutex<CurrentStateV1>,
-) -> anyhow::Result<()> {
+async fn load_comment_resources(ctx: &CohostContext) -> anyhow::Result<()> {
     let total = ctx.total_comment_resources_count().await?;
 
     info!("checking comment resource files");
 
-    let progress = ProgressBar::new(total);
+    let progress = ctx.progress.add(ProgressBar::new(total));
     progress.set_style(long_progress_style());
 
     let loaded = Arc::new(AtomicU64::new(0));
@@ -958,7 +1207,6 @@ async fn load_comment_resources(
 
         par_load_resources(
             ctx,
-            state,
             &loaded,
             &items,
             |(comment, _)| format!("comment {comment}"),
@@ -979,13 +1227,38 @@ async fn load_comment_resources(
     Ok(())
 }
 
+/// Forgets the failures and tries loading the resources again.
+async fn retry_failed_resources(ctx: &CohostContext, urls: Vec<String>) -> anyhow::Result<()> {
+    let progress = ctx.progress.add(ProgressBar::new(urls.len() as u64));
+    progress.set_style(long_progress_style());
+    progress.set_message("retrying failed resources");
+
+    for url in &urls {
+        ctx.remove_resource_failure(url).await?;
+    }
+
+    let loaded = Arc::new(AtomicU64::new(0));
+
+    for (i, chunk) in urls.chunks(RESOURCE_LOAD_BATCH_SIZE as usize).enumerate() {
+        progress.set_position(i as u64 * RESOURCE_LOAD_BATCH_SIZE);
+        par_load_resources(ctx, &loaded, chunk, |url| url.clone(), |url| url).await?;
+    }
+
+    progress.finish_and_clear();
+
+    let loaded = loaded.load(Ordering::Acquire);
+    info!("loaded {loaded} of {} resources", urls.len());
+
+    Ok(())
+}
+
 async fn migrate_resource_file_paths(
     ctx: &CohostContext,
     forget_missing: bool,
 ) -> anyhow::Result<()> {
     let total_count = ctx.total_url_file_count().await?;
 
-    let progress = ProgressBar::new(total_count);
+    let progress = ctx.progress.add(ProgressBar::new(total_count));
     progress.set_style(long_progress_style());
 
     progress.set_message("checking resource file paths");
@@ -1068,6 +1341,138 @@ to forget them from the database. Skipping for now!");
     Ok(())
 }
 
+/// Sniffs the content types of all downloaded resources.
+async fn resniff_resource_types(ctx: &CohostContext) -> anyhow::Result<()> {
+    let total_count = ctx.total_url_file_count().await?;
+
+    let progress = ctx.progress.add(ProgressBar::new(total_count));
+    progress.set_style(long_progress_style());
+
+    progress.set_message("checking resource file types");
+
+    let mut changed = 0;
+    let mut offset = 0;
+    loop {
+        progress.set_position(offset as u64);
+
+        let url_files = ctx.get_url_files_batch(offset, 1000).await?;
+
+        offset += 1000;
+        if url_files.is_empty() {
+            break;
+        }
+
+        for (url, path) in url_files {
+            let Ok(url) = Url::parse(&url) else { continue };
+            if !ctx.root_dir.join(&path).exists() {
+                continue;
+            }
+
+            match ctx.resniff_resource(&url, &path).await {
+                Ok(true) => changed += 1,
+                Ok(false) => (),
+                Err(e) => warn!("could not check file type of {}: {e:?}", path.display()),
+            }
+        }
+
+        progress.set_message(format!("checking resource file types (changed: {changed})"));
+    }
+
+    progress.finish_and_clear();
+
+    info!("changed the file type of {changed} resources");
+
+    Ok(())
+}
+
+/// Checks all downloaded resources for changes, and reports what changed.
+async fn revalidate_all_resources(ctx: &CohostContext, broken_only: bool) -> anyhow::Result<()> {
+    let total_count = ctx.total_url_file_count().await?;
+
+    let progress = ctx.progress.add(ProgressBar::new(total_count));
+    progress.set_style(long_progress_style());
+
+    progress.set_message("revalidating resources");
+
+    let mut unchanged = 0;
+    let mut hashed = 0;
+    let mut restored = 0;
+    let mut changed = 0;
+    let mut gone = 0;
+    let mut failed = 0;
+
+    let mut offset = 0;
+    loop {
+        progress.set_position(offset as u64);
+
+        let url_files = ctx.get_url_files_batch(offset, 1000).await?;
+
+        offset += 1000;
+        if url_files.is_empty() {
+            break;
+        }
+
+        for (url, path) in url_files {
+            let Ok(url) = Url::parse(&url) else { continue };
+
+            match ctx.revalidate_resource(&url, &path, broken_only).await {
+                Ok(Revalidation::Skipped) => (),
+                Ok(Revalidation::Hashed) => hashed += 1,
+                Ok(Revalidation::Unchanged) => unchanged += 1,
+                Ok(Revalidation::Restored) => {
+                    progress.suspend(|| info!("restored missing file for {url}"));
+                    restored += 1;
+                }
+                Ok(Revalidation::Changed {
+                    new_path,
+                    was_broken,
+                }) => {
+                    let reason = if was_broken {
+                        "local file was broken"
+                    } else {
+                        "changed on the server"
+                    };
+                    progress.suspend(|| {
+                        info!(
+                            "{url} {reason}:\n  new: {}\n  old: {}",
+                            new_path.display(),
+                            path.display()
+                        )
+                    });
+                    changed += 1;
+                }
+                Ok(Revalidation::Gone) => {
+                    progress.suspend(|| info!("{url} is gone (keeping {})", path.display()));
+                    gone += 1;
+                }
+                Err(e) => {
+                    progress.suspend(|| warn!("could not revalidate {url}: {e:?}"));
+                    failed += 1;
+                }
+            }
+        }
+
+        progress.set_message(format!(
+            "revalidating resources (changed: {changed}, restored: {restored})"
+        ));
+    }
+
+    progress.finish_and_clear();
+
+    info!("unchanged: {unchanged}");
+    if hashed > 0 {
+        info!("hashed for the first time: {hashed}");
+    }
+    info!("changed: {changed}");
+    info!("restored: {restored}");
+    info!("gone: {gone}");
+    if failed > 0 {
+        warn!("failed: {failed}");
+    }
+
+    Ok(())
+}
+
 fn remove_empty_dirs(base: &Path, path: &Path) -> anyhow::Result<()> {
     let mut path = PathBuf::from(path);
     while path.components().count() > 0 {
@@ -1084,16 +1489,38 @@ fn remove_empty_dirs(base: &Path, path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn make_context(config: &Config, db: SqliteConnection) -> (CohostContext, CurrentStateV1) {
+fn make_context(config: &Config, mut db: SqliteConnection) -> (CohostContext, CurrentStateV1) {
+    let mut state = ok_or_quit(CurrentStateV1::load_state().context("loading state"));
+
+    if !state.failed_urls.is_empty() {
+        ok_or_quit(
+            Database::migrate_old_failed_urls(&mut db, &state.failed_urls)
+                .context("moving failed URLs to the database"),
+        );
+        state.failed_urls.clear();
+        ok_or_quit(state.store_state().context("saving state"));
+    }
+
+    // rejected resources might be allowed by the new rules
+    let resource_rules_hash = ResourceRules::config_hash(config);
+    if state.resource_rules_hash.as_ref() != Some(&resource_rules_hash) {
+        ok_or_quit(
+            Database::remove_rule_failures(&mut db)
+                .context("resetting resources rejected by resource rules"),
+        );
+        state.resource_rules_hash = Some(resource_rules_hash);
+        ok_or_quit(state.store_state().context("saving state"));
+    }
+
     let mut ctx = CohostContext::new(
         config.cookie.clone(),
         Duration::from_secs(config.request_timeout_secs.unwrap_or(120)),
         PathBuf::from(&config.root_dir),
         db,
     );
-    ctx.do_not_fetch_domains = config.do_not_fetch_domains.iter().cloned().collect();
-
-    let state = ok_or_quit(CurrentStateV1::load_state().context("loading state"));
+    ctx.resource_rules =
+        ok_or_quit(ResourceRules::from_config(config).context("reading resource rules"));
+    ctx.post_filter = ok_or_quit(PostFilter::from_config(config).context("reading post filters"));
 
     (ctx, state)
 }
@@ -1116,30 +1543,19 @@ fn save_state_continuously(state: Arc<Mutex<CurrentStateV1>>) {
 }
 
 pub async fn download(config: Config, db: SqliteConnection) {
-    let (ctx, mut state) = make_context(&config, db);
-
-    let login = ok_or_quit(login(&ctx).await.context("logging in"));
-
-    if !state.has_follows.contains(&login.project_id) {
-        ok_or_quit(
-            load_follows(&ctx, &login, &mut state)
-                .await
-                .context("loading follows"),
-        );
-        let _ = state.store_state();
-    }
+    let (ctx, state) = make_context(&config, db);
 
     let state = Arc::new(Mutex::new(state));
 
     save_state_continuously(Arc::clone(&state));
 
-    if !state.lock().await.has_likes.contains(&login.project_id) && config.load_likes {
-        ok_or_quit(
-            load_likes(&ctx, &login, &state)
-                .await
-                .context("loading likes"),
-        );
-    }
+    let login = ok_or_quit(login(&ctx).await.context("logging in"));
+    let login = ok_or_quit(
+        restore_active_project(&ctx, &state, login)
+            .await
+            .context("switching back to the original active page"),
+    );
+    let login = ok_or_quit(load_all_follows_and_likes(&ctx, &config, &state, login).await);
 
     for handle in &config.load_profile_posts {
         let project = if !ok_or_quit(ctx.has_project_handle(handle).await) {
@@ -1173,6 +1589,7 @@ pub async fn download(config: Config, db: SqliteConnection) {
                     project,
                     new_only,
                     config.skip_inaccessible_profiles,
+                    None,
                 )
                 .await,
             );
@@ -1208,6 +1625,7 @@ pub async fn download(config: Config, db: SqliteConnection) {
                         project,
                         new_only,
                         config.skip_inaccessible_profiles,
+                        None,
                     )
                     .await,
                 );
@@ -1217,6 +1635,14 @@ pub async fn download(config: Config, db: SqliteConnection) {
         }
     }
 
+    if config.follow_crawl_depth > 0 {
+        ok_or_quit(
+            crawl_neighbourhood(&ctx, &config, &state, &login)
+                .await
+                .context("crawling neighbourhood"),
+        );
+    }
+
     for tag in &config.load_tagged_posts {
         let has_all_posts = state
             .lock()
@@ -1253,16 +1679,16 @@ pub async fn download(config: Config, db: SqliteConnection) {
 
     ok_or_quit(migrate_resource_file_paths(&ctx, config.forget_missing_url_files).await);
 
-    ok_or_quit(load_cohost_resources(&ctx, &state).await);
+    ok_or_quit(load_cohost_resources(&ctx).await);
 
     if config.load_post_resources {
-        ok_or_quit(load_post_resources(&ctx, &state).await);
+        ok_or_quit(load_post_resources(&ctx).await);
     }
     if config.load_project_resources {
-        ok_or_quit(load_project_resources(&ctx, &state).await);
+        ok_or_quit(load_project_resources(&ctx).await);
     }
     if config.load_comment_resources {
-        ok_or_quit(load_comment_resources(&ctx, &state).await);
+        ok_or_quit(load_comment_resources(&ctx).await);
     }
 
     ok_or_quit(state.lock().await.store_state());
@@ -1284,22 +1710,115 @@ pub async fn import_cdl1(
     ok_or_quit(crate::import_cdl1::import_cdl1(&ctx, &state, import_config).await);
     ok_or_quit(state.lock().await.store_state());
 
+    load_missing_resources(&config, &ctx).await;
+
+    info!("Done");
+}
+
+pub async fn import_har(config: Config, db: SqliteConnection, files: Vec<PathBuf>, add_only: bool) {
+    let (ctx, state) = make_context(&config, db);
+
+    let state = Arc::new(Mutex::new(state));
+
+    save_state_continuously(Arc::clone(&state));
+
+    for file in files {
+        ok_or_quit(crate::import_har::import_har(&ctx, &state, &file, add_only).await);
+        ok_or_quit(state.lock().await.store_state());
+    }
+
+    load_missing_resources(&config, &ctx).await;
+
+    info!("Done");
+}
+
+pub async fn import_warc(
+    config: Config,
+    db: SqliteConnection,
+    files: Vec<PathBuf>,
+    add_only: bool,
+) {
+    let (ctx, state) = make_context(&config, db);
+
+    let state = Arc::new(Mutex::new(state));
+
+    save_state_continuously(Arc::clone(&state));
+
+    for file in files {
+        ok_or_quit(crate::warc::import_warc(&ctx, &state, &file, add_only).await);
+        ok_or_quit(state.lock().await.store_state());
+    }
+
+    load_missing_resources(&config, &ctx).await;
+
+    info!("Done");
+}
+
+pub async fn resniff_resources(config: Config, db: SqliteConnection) {
+    let (ctx, _) = make_context(&config, db);
+
+    ok_or_quit(resniff_resource_types(&ctx).await);
+
+    // adds file extensions according to the new types
+    ok_or_quit(migrate_resource_file_paths(&ctx, config.forget_missing_url_files).await);
+
+    info!("Done");
+}
+
+pub async fn revalidate_resources(config: Config, db: SqliteConnection, broken_only: bool) {
+    let (ctx, _) = make_context(&config, db);
+
+    ok_or_quit(revalidate_all_resources(&ctx, broken_only).await);
+
+    info!("Done");
+}
+
+pub async fn failures(
+    config: Config,
+    db: SqliteConnection,
+    grouping: FailureGrouping,
+    filter: FailureFilter,
+    retry: bool,
+) {
+    let (ctx, _) = make_context(&config, db);
+
+    let mut failures = ok_or_quit(
+        load_failures(&ctx, &filter)
+            .await
+            .context("loading failures"),
+    );
+
+    if retry && !failures.is_empty() {
+        let urls = failures.into_iter().map(|failure| failure.url).collect();
+        ok_or_quit(retry_failed_resources(&ctx, urls).await);
+
+        failures = ok_or_quit(
+            load_failures(&ctx, &filter)
+                .await
+                .context("loading failures"),
+        );
+    }
+
+    if failures.is_empty() {
+        info!("no failures");
+    } else {
+        print_failure_groups(&group_failures(failures, grouping));
+    }
+}
+
+/// Loads resources for imported data, as configured.
+async fn load_missing_resources(config: &Config, ctx: &CohostContext) {
     info!("Now downloading any missing resources");
 
-    // load any missing resources if needed
-    ok_or_quit(load_cohost_resources(&ctx, &state).await);
+    ok_or_quit(load_cohost_resources(ctx).await);
 
     if config.load_post_resources {
-        ok_or_quit(load_post_resources(&ctx, &state).await);
+        ok_or_quit(load_post_resources(ctx).await);
     }
     if config.load_project_resources {
-        ok_or_quit(load_project_resources(&ctx, &state).await);
+        ok_or_quit(load_project_resources(ctx).await);
     }
     if config.load_comment_resources {
-        ok_or_quit(load_comment_resources(&ctx, &state).await);
+        ok_or_quit(load_comment_resources(ctx).await);
     }
-
-    ok_or_quit(state.lock().await.store_state());
-
-    info!("Done");
 }
@@ -0,0 +1,204 @@
+use crate::data::{Database, DbResourceFailure, ResourceReferences};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureGrouping {
+    #[default]
+    Host,
+    Status,
+    Class,
+}
+
+/// Selects failures. Unset fields match everything.
+#[derive(Debug, Default)]
+pub struct FailureFilter {
+    /// Host name, also matching its subdomains
+    pub host: Option<String>,
+    pub status: Option<u16>,
+    /// Error class, like `NotFound` or `Rule`
+    pub class: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureReference {
+    pub description: String,
+    pub href: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceFailure {
+    pub url: String,
+    pub host: Option<String>,
+    pub error_class: String,
+    pub status_code: Option<u16>,
+    pub message: String,
+    pub attempts: u32,
+    pub first_failed_at: String,
+    pub last_failed_at: String,
+    /// If true, the downloader won't try again
+    pub skip: bool,
+    pub references: Vec<FailureReference>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureGroup {
+    pub key: String,
+    pub failures: Vec<ResourceFailure>,
+}
+
+impl FailureFilter {
+    fn matches(&self, failure: &ResourceFailure) -> bool {
+        if let Some(host) = &self.host {
+            let host = host.to_ascii_lowercase();
+            let matches_host = failure.host.as_deref().map_or(false, |failure_host| {
+                failure_host == host || failure_host.ends_with(&format!(".{host}"))
+            });
+            if !matches_host {
+                return false;
+            }
+        }
+        if self.status.is_some() && failure.status_code != self.status {
+            return false;
+        }
+        if let Some(class) = &self.class {
+            if !failure.error_class.eq_ignore_ascii_case(class) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl FailureGrouping {
+    fn key(self, failure: &ResourceFailure) -> String {
+        match self {
+            FailureGrouping::Host => failure.host.clone().unwrap_or("(no host)".into()),
+            FailureGrouping::Status => failure
+                .status_code
+                .map(|status| status.to_string())
+                .unwrap_or("(no status)".into()),
+            FailureGrouping::Class => failure.error_class.clone(),
+        }
+    }
+}
+
+fn references(refs: ResourceReferences) -> Vec<FailureReference> {
+    let posts = refs
+        .posts
+        .into_iter()
+        .map(|(post, handle)| FailureReference {
+            description: format!("post {post} by @{handle}"),
+            href: format!("/{handle}/post/{post}"),
+        });
+    let projects = refs
+        .projects
+        .into_iter()
+        .map(|(_, handle)| FailureReference {
+            description: format!("page @{handle}"),
+            href: format!("/{handle}"),
+        });
+    let comments = refs
+        .comments
+        .into_iter()
+        .map(|(comment, post, handle)| FailureReference {
+            description: format!("comment on post {post} by @{handle}"),
+            href: format!("/{handle}/post/{post}#comment-{comment}"),
+        });
+
+    posts.chain(projects).chain(comments).collect()
+}
+
+/// Loads all failures matching the filter, most recent first.
+pub async fn load_failures(
+    db: &Database,
+    filter: &FailureFilter,
+) -> anyhow::Result<Vec<ResourceFailure>> {
+    let failures = db.get_resource_failures().await?;
+    let mut refs = db.get_resource_failure_references().await?;
+
+    let failures = failures
+        .into_iter()
+        .map(|failure: DbResourceFailure| ResourceFailure {
+            host: Url::parse(&failure.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_string())),
+            references: references(refs.remove(&failure.url).unwrap_or_default()),
+            url: failure.url,
+            error_class: failure.error_class,
+            status_code: failure.status_code.map(|status| status as u16),
+            message: failure.message,
+            attempts: failure.attempts as u32,
+            first_failed_at: failure.first_failed_at,
+            last_failed_at: failure.last_failed_at,
+            skip: failure.skip,
+        })
+        .filter(|failure| filter.matches(failure))
+        .collect();
+
+    Ok(failures)
+}
+
+/// Groups failures, largest group first.
+pub fn group_failures(
+    failures: Vec<ResourceFailure>,
+    grouping: FailureGrouping,
+) -> Vec<FailureGroup> {
+    let mut groups = BTreeMap::<String, Vec<ResourceFailure>>::new();
+    for failure in failures {
+        groups
+            .entry(grouping.key(&failure))
+            .or_default()
+            .push(failure);
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .map(|(key, failures)| FailureGroup { key, failures })
+        .collect();
+    groups.sort_by(|a, b| b.failures.len().cmp(&a.failures.len()));
+    groups
+}
+
+pub fn print_failure_groups(groups: &[FailureGroup]) {
+    for group in groups {
+        let count = group.failures.len();
+        println!(
+            "{} ({count} {})",
+            group.key,
+            if count == 1 { "failure" } else { "failures" }
+        );
+
+        for failure in &group.failures {
+            println!("    {}", failure.url);
+
+            let status = failure
+                .status_code
+                .map(|status| format!(" {status}"))
+                .unwrap_or_default();
+            let attempts = if failure.attempts == 1 {
+                "1 attempt".to_string()
+            } else {
+                format!("{} attempts", failure.attempts)
+            };
+            let retry = if failure.skip {
+                "not retried"
+            } else {
+                "retried on next download"
+            };
+            println!(
+                "        {}{status}, {attempts}, last at {}, {retry}",
+                failure.error_class, failure.last_failed_at
+            );
+            println!("        {}", failure.message);
+
+            for reference in &failure.references {
+                println!("        used in {}", reference.description);
+            }
+        }
+        println!();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::comment::Permission;
-use crate::context::CohostContext;
+use crate::context::{AuthError, CohostContext};
 use crate::post::PostFromCohost;
 use anyhow::{anyhow, Context};
 use html5ever::tendril::TendrilSink;
@@ -31,9 +31,9 @@ pub struct PostsFeed {
 }
 
 #[derive(Debug, Deserialize)]
-struct LikedPostsFeed {
+pub(crate) struct LikedPostsFeed {
     #[serde(rename = "liked-posts-feed")]
-    liked_posts_feed: PostsFeed,
+    pub liked_posts_feed: PostsFeed,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,7 +44,6 @@ pub struct TaggedPostsFeed {
     pub pagination_mode: PaginationMode,
     pub posts: Vec<PostFromCohost>,
     pub synonyms_and_related_tags: Vec<RelatedTag>,
-    #[allow(unused)]
     pub tag_name: String,
     #[allow(unused)]
     pub show_18_plus_posts: bool,
@@ -66,9 +65,9 @@ pub enum TagRelationship {
 }
 
 #[derive(Debug, Deserialize)]
-struct TaggedPostFeedContainer {
+pub(crate) struct TaggedPostFeedContainer {
     #[serde(rename = "tagged-post-feed")]
-    tagged_post_feed: TaggedPostsFeed,
+    pub tagged_post_feed: TaggedPostsFeed,
 }
 
 // Not a feed, but this is the file where all the others of this type are
@@ -98,6 +97,23 @@ impl CohostContext {
         &self,
         ref_timestamp: Option<u64>,
         skip_posts: u64,
+    ) -> anyhow::Result<PostsFeed> {
+        loop {
+            let result = self.load_liked_posts_once(ref_timestamp, skip_posts).await;
+            if self
+                .retry_if_logged_out(&result, |r: &PostsFeed| &r.posts)
+                .await?
+            {
+                continue;
+            }
+            break result;
+        }
+    }
+
+    async fn load_liked_posts_once(
+        &self,
+        ref_timestamp: Option<u64>,
+        skip_posts: u64,
     ) -> anyhow::Result<PostsFeed> {
         let mut url = Url::parse("https://cohost.org/rc/liked-posts")?;
         if let Some(ref_timestamp) = ref_timestamp {
@@ -130,6 +146,26 @@ impl CohostContext {
         tag: &str,
         ref_timestamp: Option<u64>,
         skip_posts: u64,
+    ) -> anyhow::Result<TaggedPostsFeed> {
+        loop {
+            let result = self
+                .load_tagged_posts_once(tag, ref_timestamp, skip_posts)
+                .await;
+            if self
+                .retry_if_logged_out(&result, |r: &TaggedPostsFeed| &r.posts)
+                .await?
+            {
+                continue;
+            }
+            break result;
+        }
+    }
+
+    async fn load_tagged_posts_once(
+        &self,
+        tag: &str,
+        ref_timestamp: Option<u64>,
+        skip_posts: u64,
     ) -> anyhow::Result<TaggedPostsFeed> {
         let tag_encoded = urlencoding::encode(tag);
         let mut url = Url::parse(&format!("https://cohost.org/rc/tagged/{tag_encoded}"))?;
@@ -162,6 +198,21 @@ impl CohostContext {
     }
 
     pub async fn project_page_view(&self, handle: &str) -> anyhow::Result<ProjectPageView> {
+        loop {
+            let result = self.project_page_view_once(handle).await;
+            let needs_login = result
+                .as_ref()
+                .map_or_else(AuthError::is_auth_error, |page| {
+                    page.can_access_permissions.can_read == Permission::LogInFirst
+                });
+            if needs_login && self.recover_if_logged_out().await? {
+                continue;
+            }
+            break result;
+        }
+    }
+
+    async fn project_page_view_once(&self, handle: &str) -> anyhow::Result<ProjectPageView> {
         let url = Url::parse(&format!("https://cohost.org/{handle}"))?;
 
         let html = self
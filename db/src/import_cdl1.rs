@@ -201,7 +201,7 @@ struct SinglePostViewWrapper {
 }
 
 #[derive(Deserialize)]
-struct TrpcDehydratedState {
+pub(crate) struct TrpcDehydratedState {
     queries: Vec<TrpcDehydratedQuery>,
 }
 
@@ -244,7 +244,14 @@ enum TrpcQueryKeyData {
 }
 
 impl TrpcDehydratedState {
-    fn get(&self, query_id: &str, input: Option<Value>) -> Option<&Value> {
+    /// All queries that have data, as (query ID, data).
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (String, &Value)> {
+        self.queries
+            .iter()
+            .filter_map(|q| Some((q.query_key.0.as_str(), q.state.data.as_ref()?)))
+    }
+
+    pub(crate) fn get(&self, query_id: &str, input: Option<Value>) -> Option<&Value> {
         self.queries
             .iter()
             .find(|q| {
@@ -262,17 +269,23 @@ impl TrpcDehydratedState {
     }
 }
 
-async fn import_post_page(
+/// Post data imported from a post page.
+pub(crate) struct ImportedPostPage {
+    pub post_id: u64,
+    pub handle: String,
+    pub nonce: Option<String>,
+    pub login: LoginLoggedIn,
+    /// False if the post was skipped because it already exists.
+    pub inserted: bool,
+}
+
+/// Imports post data from the HTML of a cohost.org post page.
+pub(crate) async fn import_post_page_html(
     ctx: &CohostContext,
     state: &Mutex<CurrentStateV1>,
-    file_path: &Path,
-    config: &CohostDl1ImportConfig,
-    resources: &mut HashSet<String>,
-) -> anyhow::Result<()> {
-    let html = read_to_string(&file_path)
-        .await
-        .with_context(|| format!("reading file {}", file_path.display()))?;
-
+    html: String,
+    add_only: bool,
+) -> anyhow::Result<ImportedPostPage> {
     let doc = kuchikiki::parse_html().one(html);
 
     let script = doc
@@ -293,7 +306,8 @@ async fn import_post_page(
     let login: LoginLoggedIn =
         serde_json::from_value(login.clone()).context("parsing login.loggedIn query")?;
 
-    if !ctx.has_project_id(login.project_id).await? {
+    // pages saved while logged out don't have any edited projects
+    if login.logged_in && !ctx.has_project_id(login.project_id).await? {
         let projects = trpc_data
             .get("projects.listEditedProjects", None)
             .ok_or(anyhow!(
@@ -303,7 +317,7 @@ async fn import_post_page(
             .context("parsing projects.listEditedProjects")?;
 
         for project in projects.projects {
-            ctx.insert_project(&project, config.add_only)
+            ctx.insert_project(&project, add_only)
                 .await
                 .with_context(|| format!("inserting edited project @{}", project.handle))?;
         }
@@ -333,7 +347,7 @@ async fn import_post_page(
 
     let already_has_post = ctx.has_post(single_post.post.post_id).await?;
 
-    if config.add_only && already_has_post {
+    let inserted = if add_only && already_has_post {
         // add any missing share posts
         for (i, post) in single_post.post.share_tree.iter().enumerate() {
             let prev_post = i.checked_sub(1).and_then(|i| post.share_tree.get(i));
@@ -353,31 +367,53 @@ async fn import_post_page(
                     .await?;
             }
         }
+
+        false
     } else {
-        ctx.insert_single_post(ctx, state, &login, &single_post, config.add_only)
+        ctx.insert_single_post(ctx, state, &login, &single_post, add_only)
             .await
             .context("inserting single post data")?;
 
-        // add here so that we get resources even if reload fails
-        add_all_resources_in_post(ctx, resources, spv_data.single_post_view.post_id).await?;
+        true
+    };
 
-        if config.reload {
-            let single_post = ctx
-                .posts_single_post(
-                    &spv_data.single_post_view.project.handle,
-                    spv_data.single_post_view.post_id,
-                    spv_data.single_post_view.nonce,
-                )
-                .await
-                .context("reloading post from cohost.org (adding existing data succeeded!)")?;
+    Ok(ImportedPostPage {
+        post_id: spv_data.single_post_view.post_id,
+        handle: spv_data.single_post_view.project.handle,
+        nonce: spv_data.single_post_view.nonce,
+        login,
+        inserted,
+    })
+}
 
-            ctx.insert_single_post(ctx, state, &login, &single_post, config.add_only)
-                .await
-                .context("inserting updated single post data (adding existing data succeeded!)")?;
-        }
-    }
+async fn import_post_page(
+    ctx: &CohostContext,
+    state: &Mutex<CurrentStateV1>,
+    file_path: &Path,
+    config: &CohostDl1ImportConfig,
+    resources: &mut HashSet<String>,
+) -> anyhow::Result<()> {
+    let html = read_to_string(&file_path)
+        .await
+        .with_context(|| format!("reading file {}", file_path.display()))?;
+
+    let page = import_post_page_html(ctx, state, html, config.add_only).await?;
+
+    // add here so that we get resources even if reload fails
+    add_all_resources_in_post(ctx, resources, page.post_id).await?;
 
-    add_all_resources_in_post(ctx, resources, spv_data.single_post_view.post_id).await?;
+    if page.inserted && config.reload {
+        let single_post = ctx
+            .posts_single_post(&page.handle, page.post_id, page.nonce)
+            .await
+            .context("reloading post from cohost.org (adding existing data succeeded!)")?;
+
+        ctx.insert_single_post(ctx, state, &page.login, &single_post, config.add_only)
+            .await
+            .context("inserting updated single post data (adding existing data succeeded!)")?;
+
+        add_all_resources_in_post(ctx, resources, page.post_id).await?;
+    }
 
     Ok(())
 }
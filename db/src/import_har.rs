@@ -0,0 +1,479 @@
+use crate::context::CohostContext;
+use crate::dl::{long_progress_style, CurrentStateV1};
+use crate::feed::{LikedPostsFeed, TaggedPostFeedContainer, TaggedPostsFeed};
+use crate::import_cdl1::TrpcDehydratedState;
+use crate::post::PostFromCohost;
+use crate::trpc::{ListEditedProjects, LoginLoggedIn, ProfilePosts, SinglePost};
+use anyhow::{anyhow, Context};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use html5ever::tendril::TendrilSink;
+use indicatif::ProgressBar;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use tokio::fs::read_to_string;
+use tokio::sync::Mutex;
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: HarResponse,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    #[serde(default)]
+    status: u16,
+    content: HarContent,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    #[serde(default)]
+    mime_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+}
+
+impl HarContent {
+    fn data(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(text) = &self.text else {
+            return Ok(None);
+        };
+        if self.encoding.as_deref() == Some("base64") {
+            let data = BASE64_STANDARD
+                .decode(text)
+                .context("decoding base64 response")?;
+            Ok(Some(data))
+        } else {
+            Ok(Some(text.as_bytes().to_vec()))
+        }
+    }
+
+    fn text(&self) -> anyhow::Result<Option<String>> {
+        match self.data()? {
+            Some(data) => Ok(Some(
+                String::from_utf8(data).context("response is not UTF-8")?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Data found in a HAR entry.
+enum HarItem {
+    /// tRPC query results, as (query ID, data)
+    Trpc(Vec<(String, Value)>),
+    /// a cohost.org page with its loader state
+    Page {
+        loader_state: Option<Value>,
+        queries: Vec<(String, Value)>,
+    },
+    /// an image or other media file
+    Resource {
+        url: Url,
+        content_type: String,
+        data: Vec<u8>,
+    },
+}
+
+fn read_har_entry(entry: &HarEntry) -> anyhow::Result<Option<HarItem>> {
+    if entry.response.status != 200 {
+        return Ok(None);
+    }
+
+    let Ok(url) = Url::parse(&entry.request.url) else {
+        return Ok(None);
+    };
+
+    let mime_type = &entry.response.content.mime_type;
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if url.domain() == Some("cohost.org") {
+        if let Some(names) = url.path().strip_prefix("/api/v1/trpc/") {
+            let Some(text) = entry.response.content.text()? else {
+                return Ok(None);
+            };
+            let names: Vec<_> = names.split(',').map(|name| name.to_string()).collect();
+            let is_batch = url.query_pairs().any(|(k, v)| k == "batch" && v == "1");
+
+            let response: Value = serde_json::from_str(&text).context("parsing tRPC response")?;
+            let responses = if is_batch {
+                match response {
+                    Value::Array(items) => items,
+                    _ => return Err(anyhow!("tRPC batch response is not an array")),
+                }
+            } else {
+                vec![response]
+            };
+
+            let mut results = Vec::new();
+            for (name, mut response) in names.into_iter().zip(responses) {
+                if let Some(data) = response.pointer_mut("/result/data") {
+                    results.push((name, data.take()));
+                }
+            }
+
+            return Ok(Some(HarItem::Trpc(results)));
+        }
+
+        if essence == "text/html" {
+            let Some(html) = entry.response.content.text()? else {
+                return Ok(None);
+            };
+            let doc = kuchikiki::parse_html().one(html);
+
+            let loader_state = match doc.select_first("script#__COHOST_LOADER_STATE__") {
+                Ok(script) => Some(
+                    serde_json::from_str(&script.text_contents())
+                        .context("parsing __COHOST_LOADER_STATE__")?,
+                ),
+                Err(()) => None,
+            };
+
+            let queries = match doc.select_first("script#trpc-dehydrated-state") {
+                Ok(script) => {
+                    let state: TrpcDehydratedState = serde_json::from_str(&script.text_contents())
+                        .context("parsing trpc-dehydrated-state")?;
+                    state
+                        .iter()
+                        .map(|(name, data)| (name, data.clone()))
+                        .collect()
+                }
+                Err(()) => Vec::new(),
+            };
+
+            return Ok(Some(HarItem::Page {
+                loader_state,
+                queries,
+            }));
+        }
+    }
+
+    if essence.starts_with("image/")
+        || essence.starts_with("audio/")
+        || essence.starts_with("video/")
+    {
+        let Some(data) = entry.response.content.data()? else {
+            return Ok(None);
+        };
+        return Ok(Some(HarItem::Resource {
+            url,
+            content_type: mime_type.clone(),
+            data,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Parses post data.
+/// If we don't know who was logged in, likes can't be attributed to anyone, so they are removed.
+fn parse_posts<T: DeserializeOwned>(mut data: Value, keep_likes: bool) -> serde_json::Result<T> {
+    fn remove_likes(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                if let Some(is_liked) = object.get_mut("isLiked") {
+                    *is_liked = Value::Bool(false);
+                }
+                for value in object.values_mut() {
+                    remove_likes(value);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    remove_likes(item);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !keep_likes {
+        remove_likes(&mut data);
+    }
+    serde_json::from_value(data)
+}
+
+struct HarImport<'a> {
+    ctx: &'a CohostContext,
+    state: &'a Mutex<CurrentStateV1>,
+    login: LoginLoggedIn,
+    keep_likes: bool,
+    add_only: bool,
+}
+
+impl HarImport<'_> {
+    async fn insert_post(&self, post: &PostFromCohost) -> anyhow::Result<()> {
+        if self.add_only && self.ctx.has_post(post.post_id).await? {
+            return Ok(());
+        }
+
+        self.ctx
+            .insert_post(
+                self.ctx,
+                self.state,
+                &self.login,
+                post,
+                false,
+                None,
+                self.add_only,
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "inserting post {}/{}",
+                    post.posting_project.handle, post.filename
+                )
+            })
+    }
+
+    async fn import_query(&self, name: &str, data: Value) -> anyhow::Result<()> {
+        match name {
+            "posts.profilePosts" => {
+                let data: ProfilePosts =
+                    parse_posts(data, self.keep_likes).context("parsing posts.profilePosts")?;
+                for post in &data.posts {
+                    self.insert_post(post).await?;
+                }
+            }
+            "posts.singlePost" => {
+                let data: SinglePost =
+                    parse_posts(data, self.keep_likes).context("parsing posts.singlePost")?;
+                if self.add_only && self.ctx.has_post(data.post.post_id).await? {
+                    return Ok(());
+                }
+                self.ctx
+                    .insert_single_post(self.ctx, self.state, &self.login, &data, self.add_only)
+                    .await
+                    .context("inserting single post data")?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn import_loader_state(&self, loader_state: Value) -> anyhow::Result<()> {
+        let is_liked_feed = loader_state.get("liked-posts-feed").is_some();
+        let is_tag_feed = loader_state.get("tagged-post-feed").is_some();
+
+        if is_liked_feed {
+            let feed: LikedPostsFeed =
+                parse_posts(loader_state, self.keep_likes).context("parsing liked posts feed")?;
+            for post in &feed.liked_posts_feed.posts {
+                self.insert_post(post).await?;
+            }
+        } else if is_tag_feed {
+            let feed: TaggedPostFeedContainer =
+                parse_posts(loader_state, self.keep_likes).context("parsing tagged posts feed")?;
+            let feed = feed.tagged_post_feed;
+            for post in &feed.posts {
+                self.insert_post(post).await?;
+            }
+            self.import_related_tags(&feed).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if a file was stored.
+    async fn import_item(&self, item: HarItem) -> anyhow::Result<bool> {
+        match item {
+            HarItem::Trpc(queries) => {
+                for (name, data) in queries {
+                    self.import_query(&name, data).await?;
+                }
+            }
+            HarItem::Page {
+                loader_state,
+                queries,
+            } => {
+                for (name, data) in queries {
+                    self.import_query(&name, data).await?;
+                }
+                if let Some(loader_state) = loader_state {
+                    self.import_loader_state(loader_state).await?;
+                }
+            }
+            HarItem::Resource {
+                url,
+                content_type,
+                data,
+            } => {
+                let path = self
+                    .ctx
+                    .store_resource(&url, &content_type, data.as_slice())
+                    .await
+                    .with_context(|| format!("storing {url}"))?;
+                return Ok(path.is_some());
+            }
+        }
+        Ok(false)
+    }
+
+    async fn import_related_tags(&self, feed: &TaggedPostsFeed) -> anyhow::Result<()> {
+        let tag = &feed.tag_name;
+        let canonical = feed
+            .synonyms_and_related_tags
+            .iter()
+            .find(|item| item.content.to_lowercase() == tag.to_lowercase())
+            .map(|item| item.content.as_str())
+            .unwrap_or(tag);
+
+        for item in &feed.synonyms_and_related_tags {
+            if item.content == canonical {
+                // self relationship is always synonym
+                continue;
+            }
+
+            self.ctx
+                .insert_related_tags(canonical, &item.content, item.relationship)
+                .await
+                .with_context(|| format!("inserting related tag for #{tag}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn import_har(
+    ctx: &CohostContext,
+    state: &Mutex<CurrentStateV1>,
+    file_path: &Path,
+    add_only: bool,
+) -> anyhow::Result<()> {
+    let har = read_to_string(file_path)
+        .await
+        .with_context(|| format!("reading file {}", file_path.display()))?;
+    let har: Har = serde_json::from_str(&har).context("parsing HAR file")?;
+
+    let progress = ProgressBar::new(har.log.entries.len() as u64);
+    progress.set_style(long_progress_style());
+    progress.set_message("reading entries");
+
+    let mut items = Vec::new();
+    for entry in &har.log.entries {
+        progress.inc(1);
+        match read_har_entry(entry) {
+            Ok(Some(item)) => items.push(item),
+            Ok(None) => (),
+            Err(e) => {
+                progress.suspend(|| warn!("skipping {}: {e:?}", entry.request.url));
+            }
+        }
+    }
+    progress.finish_and_clear();
+
+    // find out who was logged in, so that likes can be attributed correctly
+    let queries = items.iter().flat_map(|item| match item {
+        HarItem::Trpc(queries) => queries.as_slice(),
+        HarItem::Page { queries, .. } => queries.as_slice(),
+        HarItem::Resource { .. } => &[],
+    });
+
+    let mut login = None;
+    let mut edited_projects = None;
+    for (name, data) in queries {
+        match name.as_str() {
+            "login.loggedIn" if login.is_none() => {
+                let data: LoginLoggedIn =
+                    serde_json::from_value(data.clone()).context("parsing login.loggedIn query")?;
+                if data.logged_in {
+                    login = Some(data);
+                }
+            }
+            "projects.listEditedProjects" if edited_projects.is_none() => {
+                let data: ListEditedProjects = serde_json::from_value(data.clone())
+                    .context("parsing projects.listEditedProjects")?;
+                edited_projects = Some(data);
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(projects) = edited_projects {
+        for project in projects.projects {
+            ctx.insert_project(&project, add_only)
+                .await
+                .with_context(|| format!("inserting edited project @{}", project.handle))?;
+        }
+    }
+
+    let mut keep_likes = false;
+    if let Some(login) = &login {
+        keep_likes = ctx.has_project_id(login.project_id).await?;
+    }
+    if !keep_likes {
+        warn!("could not find out which page was logged in; likes will not be imported");
+    }
+
+    let import = HarImport {
+        ctx,
+        state,
+        login: login.unwrap_or_default(),
+        keep_likes,
+        add_only,
+    };
+
+    let progress = ProgressBar::new(items.len() as u64);
+    progress.set_style(long_progress_style());
+    progress.set_message("importing");
+
+    let mut error_count = 0;
+    let mut resource_count = 0;
+
+    for item in items {
+        progress.inc(1);
+
+        match import.import_item(item).await {
+            Ok(true) => resource_count += 1,
+            Ok(false) => (),
+            Err(e) => {
+                progress.suspend(|| error!("{e:?}\n"));
+                error_count += 1;
+            }
+        }
+    }
+
+    progress.finish_and_clear();
+
+    if error_count == 0 {
+        info!(
+            "Finished importing {} ({resource_count} files)",
+            file_path.display()
+        );
+    } else {
+        info!(
+            "Finished importing {} ({resource_count} files), with {error_count} errors",
+            file_path.display()
+        );
+    }
+
+    Ok(())
+}
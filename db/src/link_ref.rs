@@ -0,0 +1,122 @@
+use crate::post::{PostBlock, PostBlockMarkdown};
+use pulldown_cmark::{Event, Tag, TagEnd};
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Projects and posts referenced by a post.
+#[derive(Debug, Default, PartialEq)]
+pub struct PostLinks {
+    /// Handles mentioned with `@handle`
+    pub mentions: HashSet<String>,
+    /// Links to cohost posts as (handle, post ID)
+    pub posts: HashSet<(String, u64)>,
+    /// Every link to cohost.org
+    pub urls: HashSet<String>,
+}
+
+impl PostLinks {
+    fn extend(&mut self, other: PostLinks) {
+        self.mentions.extend(other.mentions);
+        self.posts.extend(other.posts);
+        self.urls.extend(other.urls);
+    }
+}
+
+pub trait LinkRefs {
+    fn collect_links(&self) -> PostLinks;
+}
+
+fn mention_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[^\w@/.])@([a-zA-Z0-9-]+)").unwrap())
+}
+
+fn cohost_url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"https?://(?:www\.)?cohost\.org/[^\s"'<>()\[\]]*"#).unwrap())
+}
+
+fn post_link_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"https?://(?:www\.)?cohost\.org/([a-zA-Z0-9-]+)/post/(\d+)").unwrap()
+    })
+}
+
+/// Strings are probably markdown
+impl LinkRefs for String {
+    fn collect_links(&self) -> PostLinks {
+        let mut links = PostLinks::default();
+
+        // post links can be in markdown links, HTML, or bare text, so just look everywhere
+        for cap in post_link_regex().captures_iter(self) {
+            if let Ok(id) = cap[2].parse() {
+                links.posts.insert((cap[1].to_string(), id));
+            }
+        }
+        for url in cohost_url_regex().find_iter(self) {
+            // probably the end of a sentence rather than part of the URL
+            let url = url
+                .as_str()
+                .trim_end_matches(['.', ',', '!', '?', ':', ';']);
+            links.urls.insert(url.to_string());
+        }
+
+        // but mentions in code aren't mentions
+        let mut in_code_block = false;
+        for event in pulldown_cmark::Parser::new(self) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(TagEnd::CodeBlock) => in_code_block = false,
+                Event::Text(text) if !in_code_block => {
+                    for cap in mention_regex().captures_iter(&text) {
+                        links.mentions.insert(cap[1].to_string());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        links
+    }
+}
+
+#[test]
+fn test_collect_links() {
+    let links = "hi @someone! also @eggbug, not me@example.com or `@code`\n\
+        [this post](https://cohost.org/eggbug/post/123-a-post) and https://cohost.org/staff/post/45"
+        .to_string()
+        .collect_links();
+
+    assert_eq!(
+        links.mentions,
+        HashSet::from(["someone".into(), "eggbug".into()])
+    );
+    assert_eq!(
+        links.posts,
+        HashSet::from([("eggbug".into(), 123), ("staff".into(), 45)])
+    );
+    assert!(links.urls.contains("https://cohost.org/staff/post/45"));
+}
+
+impl LinkRefs for PostBlockMarkdown {
+    fn collect_links(&self) -> PostLinks {
+        self.content.collect_links()
+    }
+}
+
+impl LinkRefs for [PostBlock] {
+    fn collect_links(&self) -> PostLinks {
+        let mut links = PostLinks::default();
+
+        // asks were written by someone else, so only the post's own text counts
+        for block in self {
+            if let PostBlock::Markdown { markdown } = block {
+                links.extend(markdown.collect_links());
+            }
+        }
+
+        links
+    }
+}
@@ -4,7 +4,9 @@ extern crate log;
 use crate::bundled_files::TEMPLATE_CONFIG;
 use crate::context::CohostContext;
 use crate::data::Database;
+use crate::failures::{FailureFilter, FailureGrouping};
 use crate::import_cdl1::CohostDl1ImportConfig;
+use crate::resource_rules::ResourceRuleConfig;
 use anyhow::Context;
 use clap::{Parser, Subcommand};
 use diesel::connection::SimpleConnection;
@@ -14,28 +16,37 @@ use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 use serde::Deserialize;
 use std::env::{current_dir, current_exe};
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 use std::{env, fs, process};
 use tokio::time::sleep;
 
+mod browser_cookies;
 mod bundled_files;
 mod comment;
 mod context;
 mod data;
 mod dl;
+mod failures;
 mod feed;
 mod import_cdl1;
+mod import_har;
+mod link_ref;
 mod login;
 mod merge;
 mod post;
+mod post_filter;
 mod project;
 mod render;
 mod res_ref;
+mod resource_rules;
 mod schema;
 mod server;
+mod sniff;
 mod trpc;
+mod warc;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
@@ -55,8 +66,65 @@ enum Commands {
     GenerateConfig,
     /// Updates an existing config.toml with a new session cookie (interactive)
     Login,
+    /// Updates an existing config.toml with the session cookie from a browser profile
+    ///
+    /// Supports Firefox, and Chromium-based browsers on Linux.
+    ImportBrowserCookie {
+        /// Browser profile folder (or the cookie database file in it)
+        profile: String,
+    },
     /// Imports cohost-dl 1 data (interactive)
     ImportCohostDl1,
+    /// Imports posts and files from HAR files saved with browser developer tools
+    ImportHar {
+        /// HAR files
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Do not overwrite posts that already exist
+        #[arg(long)]
+        add_only: bool,
+    },
+    /// Imports post pages and files from WARC files (e.g. from the Archive Team grab)
+    ImportWarc {
+        /// WARC files (.warc or .warc.gz)
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Do not overwrite posts that already exist
+        #[arg(long)]
+        add_only: bool,
+    },
+    /// Checks the types of all downloaded files by their contents
+    ///
+    /// Updates the stored content types and adds missing file extensions.
+    ResniffResources,
+    /// Checks downloaded files for changes on the server and for local damage
+    ///
+    /// Changed or broken files are downloaded again to a new file; the old file is kept.
+    RevalidateResources {
+        /// Only download files again if they are missing or don't match the stored hash
+        #[arg(long)]
+        broken_only: bool,
+    },
+    /// Lists files that could not be downloaded
+    ///
+    /// The same list is shown at /rc/failures in the local web server.
+    Failures {
+        /// How to group the list
+        #[arg(long, value_enum, default_value_t)]
+        group_by: FailureGrouping,
+        /// Only list failures for this host (and its subdomains)
+        #[arg(long)]
+        host: Option<String>,
+        /// Only list failures with this HTTP status code
+        #[arg(long)]
+        status: Option<u16>,
+        /// Only list failures of this kind (e.g. NotFound, OtherStatus, Req, Rule)
+        #[arg(long)]
+        class: Option<String>,
+        /// Try downloading the listed files again, even if they would usually be skipped
+        #[arg(long)]
+        retry: bool,
+    },
     /// Imports data from another cohost-dl 2 download
     ///
     /// This will copy posts, comments, and files from the other download into the current download.
@@ -66,6 +134,45 @@ enum Commands {
         /// Other file data directory
         files: String,
     },
+    /// Exports posts to an EPUB file
+    ///
+    /// Posts are put in chronological order, with one chapter per post.
+    ExportEpub {
+        /// Output file
+        out: String,
+        /// Export posts by this page
+        #[arg(long)]
+        project: Option<String>,
+        /// Export posts liked by this page
+        #[arg(long)]
+        liked_by: Option<String>,
+        /// Export posts with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Book title
+        #[arg(long)]
+        title: Option<String>,
+        /// Also export comments
+        #[arg(long)]
+        comments: bool,
+    },
+    /// Exports a single post to a self-contained HTML file
+    ExportPost {
+        /// Handle of the page that posted it
+        project: String,
+        /// Post ID (or the post's file name, like 123-my-post)
+        post: String,
+        /// Output file (defaults to <project>-<post>.html)
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Exports all downloaded files and post pages to a WARC file
+    ///
+    /// If the file name ends with .gz, it will be compressed.
+    ExportWarc {
+        /// Output file (e.g. cohost.warc.gz)
+        out: String,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -77,10 +184,14 @@ pub struct Config {
     #[serde(default)]
     pub do_not_fetch_domains: Vec<String>,
     #[serde(default)]
+    pub resource_rules: Vec<ResourceRuleConfig>,
+    #[serde(default)]
     pub load_dashboard: bool,
     #[serde(default)]
     pub load_likes: bool,
     #[serde(default)]
+    pub load_all_pages: bool,
+    #[serde(default)]
     pub load_profile_posts: Vec<String>,
     #[serde(default)]
     pub load_tagged_posts: Vec<String>,
@@ -89,6 +200,14 @@ pub struct Config {
     #[serde(default)]
     pub skip_follows: Vec<String>,
     #[serde(default)]
+    pub follow_crawl_depth: u32,
+    #[serde(default = "default_follow_crawl_max_projects")]
+    pub follow_crawl_max_projects: u64,
+    #[serde(default = "default_follow_crawl_max_posts")]
+    pub follow_crawl_max_posts: u64,
+    #[serde(default)]
+    pub follow_crawl_load_posts: bool,
+    #[serde(default)]
     pub load_new_posts: bool,
     #[serde(default)]
     pub load_comments: bool,
@@ -104,7 +223,43 @@ pub struct Config {
     pub forget_missing_url_files: bool,
     #[serde(default)]
     pub skip_inaccessible_profiles: bool,
+    pub published_after: Option<String>,
+    pub published_before: Option<String>,
+    #[serde(default)]
+    pub skip_adult_content: bool,
+    #[serde(default)]
+    pub skip_cws: Vec<String>,
+    #[serde(default)]
+    pub include_tags: Vec<String>,
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    #[serde(default)]
+    pub skip_transparent_shares: bool,
+    #[serde(default = "default_server_address")]
+    pub server_address: String,
     pub server_port: u16,
+    #[serde(default)]
+    pub wayback_links: bool,
+    #[serde(default)]
+    pub offline_mode: bool,
+    #[serde(default)]
+    pub public_mirror: bool,
+    #[serde(default)]
+    pub public_opt_out: Vec<String>,
+    #[serde(default)]
+    pub public_allow_indexing: bool,
+}
+
+fn default_server_address() -> String {
+    "127.0.0.1".into()
+}
+
+fn default_follow_crawl_max_projects() -> u64 {
+    500
+}
+
+fn default_follow_crawl_max_posts() -> u64 {
+    100_000
 }
 
 fn main() {
@@ -159,12 +314,44 @@ async fn main_impl() {
                     process::exit(1);
                 }
             }
+            Commands::ImportBrowserCookie { profile } => {
+                if let Err(e) = import_browser_cookie(config, db, &profile).await {
+                    eprintln!("{e:?}");
+                    process::exit(1);
+                }
+            }
             Commands::ImportCohostDl1 => {
                 if let Err(e) = interactive_import_cdl1_data(config, db).await {
                     eprintln!("{e:?}");
                     process::exit(1);
                 }
             }
+            Commands::ImportHar { files, add_only } => {
+                let files = files.into_iter().map(PathBuf::from).collect();
+                dl::import_har(config, db, files, add_only).await;
+            }
+            Commands::ImportWarc { files, add_only } => {
+                let files = files.into_iter().map(PathBuf::from).collect();
+                dl::import_warc(config, db, files, add_only).await;
+            }
+            Commands::ResniffResources => dl::resniff_resources(config, db).await,
+            Commands::RevalidateResources { broken_only } => {
+                dl::revalidate_resources(config, db, broken_only).await
+            }
+            Commands::Failures {
+                group_by,
+                host,
+                status,
+                class,
+                retry,
+            } => {
+                let filter = FailureFilter {
+                    host,
+                    status,
+                    class,
+                };
+                dl::failures(config, db, group_by, filter, retry).await;
+            }
             Commands::MergeData {
                 database: other_db,
                 files: other_root_dir,
@@ -181,6 +368,71 @@ async fn main_impl() {
                     process::exit(1);
                 }
             }
+            Commands::ExportEpub {
+                out,
+                project,
+                liked_by,
+                tag,
+                title,
+                comments,
+            } => {
+                let selection = render::epub::EpubSelection {
+                    project,
+                    liked_by,
+                    tag,
+                };
+                if let Err(e) = render::epub::export_epub(
+                    &Database::new(db),
+                    &PathBuf::from(config.root_dir),
+                    selection,
+                    title,
+                    comments,
+                    &PathBuf::from(out),
+                )
+                .await
+                {
+                    eprintln!("{e:?}");
+                    process::exit(1);
+                }
+            }
+            Commands::ExportWarc { out } => {
+                if let Err(e) = warc::export_warc(
+                    &Database::new(db),
+                    &PathBuf::from(config.root_dir),
+                    &PathBuf::from(out),
+                )
+                .await
+                {
+                    eprintln!("{e:?}");
+                    process::exit(1);
+                }
+            }
+            Commands::ExportPost { project, post, out } => {
+                let out = out.unwrap_or_else(|| format!("{project}-{post}.html"));
+                let renderer = render::PageRenderer::new();
+                let result = renderer
+                    .render_single_post_file(
+                        &Database::new(db),
+                        &PathBuf::from(config.root_dir),
+                        &project,
+                        &post,
+                    )
+                    .await;
+
+                match result {
+                    Ok(html) => {
+                        if let Err(e) = fs::write(&out, html) {
+                            eprintln!("could not write {out}: {e}");
+                            process::exit(1);
+                        }
+                        info!("exported to {out}");
+                    }
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                }
+            }
         }
     } else {
         interactive().await;
@@ -191,6 +443,13 @@ fn init() -> anyhow::Result<(Config, SqliteConnection)> {
     let config = fs::read_to_string("config.toml").context("could not read config.toml")?;
     let config: Config = toml::from_str(&config).context("error reading config")?;
 
+    if config.follow_crawl_depth > 0 && !config.follow_crawl_load_posts {
+        anyhow::bail!(
+            "error reading config: follow_crawl_depth needs follow_crawl_load_posts, \
+            because the crawl only finds pages by loading their posts"
+        );
+    }
+
     let mut db =
         SqliteConnection::establish(&config.database).context("could not open database")?;
     db.batch_execute("pragma foreign_keys = on; pragma journal_mode = WAL;")
@@ -202,6 +461,8 @@ fn init() -> anyhow::Result<(Config, SqliteConnection)> {
 
     Database::migrate_old_url_files(&mut db)?;
     Database::migrate_posts(&mut db)?;
+    Database::migrate_asks(&mut db)?;
+    Database::migrate_post_links(&mut db)?;
 
     Ok((config, db))
 }
@@ -430,54 +691,78 @@ async fn interactive_setup() -> anyhow::Result<()> {
     println!("   or would you like to provide your own session cookie?");
     println!();
 
-    let cookie = loop {
-        let login = interactive_yn("Have the wizard log you in?")?;
-
-        if login {
-            match interactive_login().await? {
-                Some(result) => break result,
-                None => continue,
-            }
-        } else {
-            let cookie = rl_parse("session cookie: ", |i| {
-                if i.is_empty() {
-                    return Some(String::new());
-                }
-
-                let header = i.trim();
-                let header_lower = header.to_ascii_lowercase();
-                if !header_lower.starts_with("connect.sid=s%3a") {
-                    println!("This does not appear to be a valid session cookie.");
-                    println!("It should look something like `connect.sid=s%3AB8…<lots of base64>");
-                    return None;
+    let (cookie, login, projects) = loop {
+        let cookie = loop {
+            println!("(1) log in with email and password");
+            println!("(2) read the session cookie from your web browser");
+            println!("(3) enter a session cookie");
+            let method = rl_parse("> ", |i| match i {
+                "1" | "2" | "3" => Some(i.to_string()),
+                _ => {
+                    println!("Enter 1, 2, or 3");
+                    None
                 }
-
-                Some(header.to_string())
             })?;
 
-            if !cookie.is_empty() {
-                break cookie;
-            }
+            if method == "1" {
+                match interactive_login().await? {
+                    Some(result) => break result,
+                    None => continue,
+                }
+            } else if method == "2" {
+                match interactive_browser_cookie()? {
+                    Some(result) => break result,
+                    None => continue,
+                }
+            } else {
+                let cookie = rl_parse("session cookie: ", |i| {
+                    if i.is_empty() {
+                        return Some(String::new());
+                    }
+
+                    let header = i.trim();
+                    let header_lower = header.to_ascii_lowercase();
+                    if !header_lower.starts_with("connect.sid=s%3a") {
+                        println!("This does not appear to be a valid session cookie.");
+                        println!(
+                            "It should look something like `connect.sid=s%3AB8…<lots of base64>"
+                        );
+                        return None;
+                    }
+
+                    Some(header.to_string())
+                })?;
+
+                if !cookie.is_empty() {
+                    break cookie;
+                }
+            };
         };
-    };
-
-    println!();
-    println!("Checking...");
 
-    let db = SqliteConnection::establish(&database).context("opening database")?;
-    let ctx = CohostContext::new(
-        cookie.clone(),
-        Duration::from_secs(60),
-        PathBuf::from(&out_path),
-        db,
-    );
-    let login = ctx.login_logged_in().await.context("getting login info")?;
-    let projects = ctx
-        .projects_list_edited_projects()
-        .await
-        .context("getting login info")?;
+        println!();
+        println!("Checking...");
+
+        let db = SqliteConnection::establish(&database).context("opening database")?;
+        let ctx = CohostContext::new(
+            cookie.clone(),
+            Duration::from_secs(60),
+            PathBuf::from(&out_path),
+            db,
+        );
+        let login = ctx.login_logged_in().await.context("getting login info")?;
+        if !login.logged_in {
+            println!("This session cookie is not logged in. It may have expired.");
+            println!("Please try again.");
+            println!();
+            continue;
+        }
+        let projects = ctx
+            .projects_list_edited_projects()
+            .await
+            .context("getting login info")?;
 
-    drop(ctx);
+        break (cookie, login, projects);
+    };
 
     let current_handle = projects
         .projects
@@ -488,12 +773,6 @@ async fn interactive_setup() -> anyhow::Result<()> {
 
     println!("Logged in as {current_handle}");
 
-    if projects.projects.len() > 1 {
-        println!("Your account has access to pages other than {current_handle}!");
-        println!("cohost-dl currently can’t switch the active page.");
-        println!("If you used a browser cookie to log in, you can switch pages in the browser.");
-    }
-
     println!();
     println!("4. What do you want to download?");
 
@@ -511,6 +790,15 @@ async fn interactive_setup() -> anyhow::Result<()> {
     println!("  That’s probably a lot of posts.");
     let load_dashboard = interactive_yn("Download dashboard?")?;
 
+    let mut load_all_pages = false;
+    if projects.projects.len() > 1 && (load_likes || load_dashboard) {
+        println!();
+        println!("- Your account has access to pages other than {current_handle}!");
+        println!("  Also download liked posts and dashboards for those pages?");
+        println!("  cohost-dl will temporarily switch the active page to do this.");
+        load_all_pages = interactive_yn("Download for all pages?")?;
+    }
+
     println!();
     println!("- Download comments on posts?");
     println!("  This isn’t lots of data; it just takes a while.");
@@ -536,6 +824,9 @@ async fn interactive_setup() -> anyhow::Result<()> {
     if load_dashboard {
         config["load_dashboard"] = toml_edit::value(true);
     }
+    if load_all_pages {
+        config["load_all_pages"] = toml_edit::value(true);
+    }
     if load_comments {
         config["load_comments"] = toml_edit::value(true);
     }
@@ -574,25 +865,145 @@ async fn interactive_setup() -> anyhow::Result<()> {
 }
 
 async fn interactive_login_with_existing_config() -> anyhow::Result<()> {
-    let config = fs::read_to_string("config.toml")?;
-
-    let mut config = toml_edit::DocumentMut::from_str(&config)?;
-
     let Some(cookie) = interactive_login().await? else {
         println!("Leaving configuration as-is. Bye!");
         sleep(Duration::from_secs(2)).await;
         return Ok(());
     };
 
-    config["cookie"] = toml_edit::value(cookie);
-
-    fs::write("config.toml", config.to_string())?;
+    save_config_cookie(&cookie)?;
 
     println!("Success! You can now restart the program again to do whatever.");
     sleep(Duration::from_secs(5)).await;
     Ok(())
 }
 
+fn interactive_browser_cookie() -> anyhow::Result<Option<String>> {
+    println!("Close your browser first, so that it saves its cookies.");
+    println!("You can find your browser profile folder in:");
+    println!("- Firefox: about:profiles (Root Directory)");
+    println!("- Chromium-based browsers: chrome://version (Profile Path)");
+    println!("(This only works for Chromium-based browsers on Linux.)");
+
+    loop {
+        println!("Enter the path to your browser profile folder, or type 'exit' to go back.");
+        let profile = rl_parse("profile folder: ", |i| Some(i.to_string()))?;
+        let profile = profile.trim();
+
+        if profile == "exit" {
+            return Ok(None);
+        }
+        if profile.is_empty() {
+            continue;
+        }
+
+        match browser_cookies::read_session_cookie(&PathBuf::from(profile)) {
+            Ok(cookie) => return Ok(Some(cookie)),
+            Err(e) => println!("Error: {e:?}"),
+        }
+    }
+}
+
+async fn import_browser_cookie(
+    config: Config,
+    db: SqliteConnection,
+    profile: &str,
+) -> anyhow::Result<()> {
+    let cookie = browser_cookies::read_session_cookie(&PathBuf::from(profile))?;
+
+    let ctx = CohostContext::new(
+        cookie.clone(),
+        Duration::from_secs(config.request_timeout_secs.unwrap_or(60)),
+        PathBuf::from(&config.root_dir),
+        db,
+    );
+    let login = ctx.login_logged_in().await.context("getting login info")?;
+    if !login.logged_in {
+        anyhow::bail!(
+            "The session cookie in this browser profile is not logged in. It may have expired."
+        );
+    }
+
+    save_config_cookie(&cookie)?;
+
+    println!("Saved the session cookie to config.toml.");
+    Ok(())
+}
+
+fn read_config_cookie() -> anyhow::Result<String> {
+    let config = fs::read_to_string("config.toml").context("could not read config.toml")?;
+    let config = toml_edit::DocumentMut::from_str(&config).context("error reading config")?;
+    let cookie = config
+        .get("cookie")
+        .and_then(|cookie| cookie.as_str())
+        .context("config.toml has no cookie")?;
+    Ok(cookie.to_string())
+}
+
+fn save_config_cookie(cookie: &str) -> anyhow::Result<()> {
+    let config = fs::read_to_string("config.toml").context("could not read config.toml")?;
+    let mut config = toml_edit::DocumentMut::from_str(&config).context("error reading config")?;
+    config["cookie"] = toml_edit::value(cookie);
+    fs::write("config.toml", config.to_string()).context("saving configuration")?;
+    Ok(())
+}
+
+/// Asks for a new session cookie after the login session expired while downloading.
+async fn interactive_session_expired(old_cookie: &str) -> anyhow::Result<String> {
+    if !std::io::stdin().is_terminal() {
+        warn!("waiting for a new session cookie in config.toml");
+        loop {
+            sleep(Duration::from_secs(60)).await;
+            let cookie = read_config_cookie()?;
+            if cookie != old_cookie {
+                return Ok(cookie);
+            }
+        }
+    }
+
+    println!();
+    println!("Downloading is paused because your Cohost login session expired.");
+
+    loop {
+        println!("(1) log in again with email and password");
+        println!("(2) read the session cookie from your web browser");
+        println!("(3) I put a new session cookie in config.toml");
+        println!("(4) stop downloading");
+
+        let option = rl_parse("> ", |i| match i {
+            "1" | "2" | "3" | "4" => Some(i.to_string()),
+            _ => {
+                println!("Enter 1, 2, 3, or 4");
+                None
+            }
+        })?;
+
+        match &*option {
+            "1" => {
+                if let Some(cookie) = interactive_login().await? {
+                    save_config_cookie(&cookie)?;
+                    return Ok(cookie);
+                }
+            }
+            "2" => {
+                if let Some(cookie) = interactive_browser_cookie()? {
+                    save_config_cookie(&cookie)?;
+                    return Ok(cookie);
+                }
+            }
+            "3" => {
+                let cookie = read_config_cookie()?;
+                if cookie == old_cookie {
+                    println!("The cookie in config.toml is still the same one.");
+                } else {
+                    return Ok(cookie);
+                }
+            }
+            _ => anyhow::bail!("login session expired"),
+        }
+    }
+}
+
 async fn interactive_login() -> anyhow::Result<Option<String>> {
     let (cookie, needs_otp) = loop {
         println!("Enter your Cohost login email address, or type 'exit' to go back.");
@@ -650,6 +1061,9 @@ impl Config {
         if self.load_likes {
             println!("- will load liked posts (if not already loaded)");
         }
+        if self.load_all_pages {
+            println!("- will load follows and liked posts for all of your pages");
+        }
         match self.load_profile_posts.len() {
             0 => (),
             1 => println!("- will load posts from 1 page (if not already loaded)"),
@@ -665,6 +1079,22 @@ impl Config {
             1 => println!("- will load 1 specific post from URL (if not already loaded)"),
             n => println!("- will load {n} specific posts from URLs (if not already loaded)"),
         }
+        if self.follow_crawl_depth > 0 {
+            println!(
+                "- will crawl pages around the ones you follow (depth {}, up to {} pages)",
+                self.follow_crawl_depth, self.follow_crawl_max_projects
+            );
+        }
+        if self.published_after.is_some()
+            || self.published_before.is_some()
+            || self.skip_adult_content
+            || !self.skip_cws.is_empty()
+            || !self.include_tags.is_empty()
+            || !self.exclude_tags.is_empty()
+            || self.skip_transparent_shares
+        {
+            println!("- will only store posts that match the post filters (and posts they share)");
+        }
         if self.load_new_posts {
             println!("- will check every project for new posts");
         }
@@ -0,0 +1,128 @@
+use crate::post::PostFromCohost;
+use crate::Config;
+use anyhow::{bail, Context};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Decides which posts get stored by the downloader.
+///
+/// Posts that are only inserted as part of another post's share tree are never filtered, since
+/// the share would be broken without them.
+#[derive(Debug, Default)]
+pub struct PostFilter {
+    published_after: Option<DateTime<Utc>>,
+    published_before: Option<DateTime<Utc>>,
+    skip_adult_content: bool,
+    skip_cws: Vec<String>,
+    include_tags: Vec<String>,
+    exclude_tags: Vec<String>,
+    skip_transparent_shares: bool,
+}
+
+/// Parses either a full RFC 3339 date (`2024-01-31T12:00:00Z`) or just a day (`2024-01-31`).
+fn parse_date(date: &str) -> anyhow::Result<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date.to_utc());
+    }
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("invalid date {date:?}"))?;
+    Ok(day.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+fn normalize(items: &[String]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl PostFilter {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let published_after = config
+            .published_after
+            .as_deref()
+            .map(parse_date)
+            .transpose()
+            .context("published_after")?;
+        let published_before = config
+            .published_before
+            .as_deref()
+            .map(parse_date)
+            .transpose()
+            .context("published_before")?;
+
+        if let (Some(after), Some(before)) = (published_after, published_before) {
+            if after >= before {
+                bail!("published_after must be earlier than published_before");
+            }
+        }
+
+        Ok(Self {
+            published_after,
+            published_before,
+            skip_adult_content: config.skip_adult_content,
+            skip_cws: normalize(&config.skip_cws),
+            include_tags: normalize(&config.include_tags),
+            exclude_tags: normalize(&config.exclude_tags),
+            skip_transparent_shares: config.skip_transparent_shares,
+        })
+    }
+
+    fn published_at(post: &PostFromCohost) -> Option<DateTime<Utc>> {
+        post.published_at
+            .as_deref()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.to_utc())
+    }
+
+    /// Returns true if the post should be stored.
+    pub fn accepts(&self, post: &PostFromCohost) -> bool {
+        if self.published_after.is_some() || self.published_before.is_some() {
+            let Some(published_at) = Self::published_at(post) else {
+                return false;
+            };
+            if self.published_after.map_or(false, |d| published_at < d) {
+                return false;
+            }
+            if self.published_before.map_or(false, |d| published_at >= d) {
+                return false;
+            }
+        }
+
+        if self.skip_adult_content && post.effective_adult_content {
+            return false;
+        }
+        if self.skip_transparent_shares && post.transparent_share_of_post_id.is_some() {
+            return false;
+        }
+
+        let has_any = |list: &[String], items: &[String]| {
+            items
+                .iter()
+                .any(|item| list.contains(&item.trim().to_lowercase()))
+        };
+
+        if has_any(&self.skip_cws, &post.cws) {
+            return false;
+        }
+        if has_any(&self.exclude_tags, &post.tags) {
+            return false;
+        }
+        if !self.include_tags.is_empty() && !has_any(&self.include_tags, &post.tags) {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns true if the post was published before `published_after`.
+    ///
+    /// Feeds are in reverse chronological order (except for pinned posts),
+    /// so there is no need to load any further pages after such a post.
+    pub fn is_too_old(&self, post: &PostFromCohost) -> bool {
+        let Some(after) = self.published_after else {
+            return false;
+        };
+        !post.pinned && Self::published_at(post).map_or(false, |date| date < after)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::comment::{CommentFromCohost, InnerComment, Permission};
-use crate::data::{Database, DbDataError};
-use crate::post::{LimitedVisibilityReason, PostAstMap, PostFromCohost, PostState};
+use crate::data::{Database, DbComment, DbDataError};
+use crate::post::{LimitedVisibilityReason, PostAstMap, PostBlock, PostFromCohost, PostState};
 use crate::project::ProjectFromCohost;
 use diesel::result::Error as DieselError;
+use serde::Serialize;
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -99,7 +100,19 @@ pub async fn cohost_api_post(
 
     let posting_project = cohost_api_project(db, viewer_id, post.posting_project_id as u64).await?;
 
-    let post_data = post.data()?;
+    let mut post_data = post.data()?;
+
+    for block in &mut post_data.blocks {
+        if let PostBlock::Ask { ask } = block {
+            let is_hidden = ask.asking_project.as_ref().is_some_and(|project| {
+                db.is_project_hidden(project.project_id) || db.is_handle_hidden(&project.handle)
+            });
+            if is_hidden {
+                ask.anon = true;
+                ask.asking_project = None;
+            }
+        }
+    }
 
     let tags = db.get_post_tags(post_id).await?;
 
@@ -189,43 +202,13 @@ pub async fn cohost_api_comments(
     type ByParent = HashMap<String, Vec<CommentFromCohost>>;
     let mut by_parent: ByParent = HashMap::new();
     for comment in comments {
-        let comment_data = comment.data()?;
-
-        let is_viewer_comment = comment
+        let poster = comment
             .posting_project_id
-            .map_or(false, |p| p as u64 == viewer_id);
-
-        let cohost_comment = CommentFromCohost {
-            poster: comment
-                .posting_project_id
-                .and_then(|proj| projects.get(&(proj as u64)).cloned()),
-            comment: InnerComment {
-                body: comment_data.body,
-                comment_id: comment.id.clone(),
-                children: Vec::new(),
-                deleted: comment_data.deleted,
-                has_cohost_plus: comment_data.has_cohost_plus,
-                hidden: comment_data.hidden,
-                in_reply_to: comment.in_reply_to_id.clone(),
-                post_id,
-                posted_at_iso: comment.published_at,
-            },
-            can_edit: if is_viewer_comment {
-                Permission::Allowed
-            } else {
-                Permission::NotAllowed
-            },
-            can_hide: if is_editor {
-                Permission::Allowed
-            } else {
-                Permission::NotAllowed
-            },
-            can_interact: Permission::Allowed,
-        };
-        by_parent
-            .entry(comment.in_reply_to_id.unwrap_or_default())
-            .or_default()
-            .push(cohost_comment);
+            .and_then(|proj| projects.get(&(proj as u64)).cloned());
+        let parent = comment.in_reply_to_id.clone().unwrap_or_default();
+
+        let cohost_comment = cohost_api_comment(viewer_id, comment, poster, is_editor)?;
+        by_parent.entry(parent).or_default().push(cohost_comment);
     }
 
     fn collect(by_parent: &mut ByParent, parent: &str) -> Vec<CommentFromCohost> {
@@ -252,3 +235,106 @@ pub async fn cohost_api_comments(
 
     Ok(comments)
 }
+
+/// Converts a single comment, without its replies.
+fn cohost_api_comment(
+    viewer_id: u64,
+    comment: DbComment,
+    poster: Option<ProjectFromCohost>,
+    is_editor: bool,
+) -> Result<CommentFromCohost, GetDataError> {
+    let comment_data = comment.data()?;
+
+    let is_viewer_comment = comment
+        .posting_project_id
+        .map_or(false, |p| p as u64 == viewer_id);
+
+    Ok(CommentFromCohost {
+        poster,
+        comment: InnerComment {
+            body: comment_data.body,
+            comment_id: comment.id,
+            children: Vec::new(),
+            deleted: comment_data.deleted,
+            has_cohost_plus: comment_data.has_cohost_plus,
+            hidden: comment_data.hidden,
+            in_reply_to: comment.in_reply_to_id,
+            post_id: comment.post_id as u64,
+            posted_at_iso: comment.published_at,
+        },
+        can_edit: if is_viewer_comment {
+            Permission::Allowed
+        } else {
+            Permission::NotAllowed
+        },
+        can_hide: if is_editor {
+            Permission::Allowed
+        } else {
+            Permission::NotAllowed
+        },
+        can_interact: Permission::Allowed,
+    })
+}
+
+/// A comment along with the post it was written on, and the comment it replies to.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentInContext {
+    pub comment: CommentFromCohost,
+    pub post: PostFromCohost,
+    pub in_reply_to: Option<CommentFromCohost>,
+}
+
+/// Loads comments written by a project, newest first.
+pub async fn cohost_api_project_comments(
+    db: &Database,
+    viewer_id: u64,
+    project_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<CommentInContext>, GetDataError> {
+    let comments = db.get_project_comments(project_id, offset, limit).await?;
+    let poster = cohost_api_project(db, viewer_id, project_id).await?;
+
+    let mut items = Vec::with_capacity(comments.len());
+    for comment in comments {
+        let post = match cohost_api_post(db, viewer_id, comment.post_id as u64).await {
+            Ok(post) => post,
+            // something in the share tree is hidden
+            Err(GetDataError::NotFound) => continue,
+            Err(e) => return Err(e),
+        };
+
+        let in_reply_to = match &comment.in_reply_to_id {
+            Some(parent) => match db.comment(parent).await {
+                Ok(parent) => {
+                    let parent_poster = match parent.posting_project_id {
+                        Some(project) => {
+                            Some(cohost_api_project(db, viewer_id, project as u64).await?)
+                        }
+                        None => None,
+                    };
+                    Some(cohost_api_comment(
+                        viewer_id,
+                        parent,
+                        parent_poster,
+                        post.is_editor,
+                    )?)
+                }
+                Err(DieselError::NotFound) => None,
+                Err(err) => return Err(err.into()),
+            },
+            None => None,
+        };
+
+        let comment = cohost_api_comment(viewer_id, comment, Some(poster.clone()), post.is_editor)?;
+
+        items.push(CommentInContext {
+            comment,
+            post,
+            in_reply_to,
+        });
+    }
+
+    Ok(items)
+}
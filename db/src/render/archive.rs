@@ -0,0 +1,191 @@
+use crate::data::Database;
+use crate::render::api_data::GetDataError;
+use crate::render::feed::RenderFeedError;
+use crate::render::PageRenderer;
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tera::Context;
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Serialize)]
+struct ArchiveMonth {
+    month: u32,
+    name: &'static str,
+    count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveYear {
+    year: i32,
+    count: u64,
+    months: Vec<ArchiveMonth>,
+}
+
+#[derive(Debug, Serialize)]
+struct CalendarDay {
+    day: u32,
+    count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct MonthLink {
+    year: i32,
+    month: u32,
+    name: &'static str,
+}
+
+impl MonthLink {
+    fn new(date: NaiveDate) -> Self {
+        Self {
+            year: date.year(),
+            month: date.month(),
+            name: MONTH_NAMES[date.month0() as usize],
+        }
+    }
+}
+
+/// Returns the first day of the month, if it's a valid month.
+fn first_of_month(year: i32, month: u32) -> Result<NaiveDate, GetDataError> {
+    NaiveDate::from_ymd_opt(year, month, 1).ok_or(GetDataError::NotFound)
+}
+
+/// Returns the base path for archive pages, and the project ID if it's for a single project.
+pub async fn archive_base(
+    db: &Database,
+    project: Option<&str>,
+) -> Result<(String, Option<u64>), GetDataError> {
+    match project {
+        Some(project) => {
+            let project_id = db.project_id_for_handle(project).await?;
+            Ok((format!("/{project}/archive"), Some(project_id)))
+        }
+        None => Ok(("/rc/archive".into(), None)),
+    }
+}
+
+impl PageRenderer {
+    /// Renders post counts for every month, either for one project or for all posts.
+    pub async fn render_archive(
+        &self,
+        db: &Database,
+        project: Option<&str>,
+    ) -> Result<String, RenderFeedError> {
+        let (base, project_id) = archive_base(db, project).await?;
+
+        let counts = db
+            .post_counts_by_date(project_id, "", 7)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let mut years = BTreeMap::<i32, HashMap<u32, u64>>::new();
+        for (date, count) in counts {
+            let Ok(date) = NaiveDate::parse_from_str(&format!("{date}-01"), "%Y-%m-%d") else {
+                continue;
+            };
+            years
+                .entry(date.year())
+                .or_default()
+                .insert(date.month(), count);
+        }
+
+        let years: Vec<_> = years
+            .into_iter()
+            .rev()
+            .map(|(year, counts)| ArchiveYear {
+                year,
+                count: counts.values().sum(),
+                months: (1..=12)
+                    .map(|month| ArchiveMonth {
+                        month,
+                        name: MONTH_NAMES[month as usize - 1],
+                        count: counts.get(&month).copied().unwrap_or_default(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", &project);
+        template_ctx.insert("base", &base);
+        template_ctx.insert("years", &years);
+
+        let body = self.tera.render("archive.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    /// Renders a calendar with post counts for every day of the month.
+    pub async fn render_archive_month(
+        &self,
+        db: &Database,
+        project: Option<&str>,
+        year: i32,
+        month: u32,
+    ) -> Result<String, RenderFeedError> {
+        let first = first_of_month(year, month)?;
+        let (base, project_id) = archive_base(db, project).await?;
+
+        let prefix = first.format("%Y-%m-").to_string();
+        let counts: HashMap<_, _> = db
+            .post_counts_by_date(project_id, &prefix, 10)
+            .await
+            .map_err(GetDataError::from)?
+            .into_iter()
+            .filter_map(|(date, count)| {
+                let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?;
+                Some((date.day(), count))
+            })
+            .collect();
+
+        // weeks start on monday
+        let mut weeks = Vec::new();
+        let mut week: Vec<Option<CalendarDay>> = (0..first.weekday().num_days_from_monday())
+            .map(|_| None)
+            .collect();
+
+        for date in first.iter_days().take_while(|date| date.month() == month) {
+            week.push(Some(CalendarDay {
+                day: date.day(),
+                count: counts.get(&date.day()).copied().unwrap_or_default(),
+            }));
+            if week.len() == 7 {
+                weeks.push(std::mem::take(&mut week));
+            }
+        }
+        if !week.is_empty() {
+            week.resize_with(7, || None);
+            weeks.push(week);
+        }
+
+        let prev_month = first.checked_sub_months(Months::new(1)).map(MonthLink::new);
+        let next_month = first.checked_add_months(Months::new(1)).map(MonthLink::new);
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", &project);
+        template_ctx.insert("base", &base);
+        template_ctx.insert("month", &MonthLink::new(first));
+        template_ctx.insert("count", &counts.values().sum::<u64>());
+        template_ctx.insert("weeks", &weeks);
+        template_ctx.insert("prev_month", &prev_month);
+        template_ctx.insert("next_month", &next_month);
+
+        let body = self.tera.render("archive_month.html", &template_ctx)?;
+
+        Ok(body)
+    }
+}
@@ -0,0 +1,716 @@
+use crate::comment::CommentFromCohost;
+use crate::data::{Database, PostQuery};
+use crate::dl::long_progress_style;
+use crate::post::PostFromCohost;
+use crate::render::api_data::cohost_api_comments_for_share_tree;
+use crate::render::feed::RenderedPosts;
+use crate::render::rewrite::resolve_resource_url;
+use crate::render::PageRenderer;
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use deno_core::url::Url;
+use html5ever::tendril::TendrilSink;
+use html5ever::{namespace_url, ns};
+use indicatif::ProgressBar;
+use kuchikiki::{NodeData, NodeRef};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tera::Context;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/package.opf" media-type="application/oebps-package+xml" />
+    </rootfiles>
+</container>
+"#;
+
+const STYLE_CSS: &str = r#"body { font-family: serif; line-height: 1.4; }
+.post-header { font-family: sans-serif; font-size: 0.9em; margin-top: 1.5em; color: #555; }
+.post-header .handle { font-weight: bold; color: #000; }
+.post-tags { font-family: sans-serif; font-size: 0.8em; color: #555; }
+.post-tombstone, .cw { font-family: sans-serif; border: 1px solid #aaa; padding: 0.5em 1em; }
+.comments { margin-top: 2em; border-top: 1px solid #aaa; font-size: 0.9em; }
+.comment { margin: 0.5em 0 0.5em 0; }
+.comment-replies { margin-left: 1.5em; }
+.comment-header { font-family: sans-serif; color: #555; }
+img { max-width: 100%; }
+"#;
+
+/// EPUB core media types for images. Other formats would require fallbacks, so they're linked instead.
+const EPUB_IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/gif", "gif"),
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/svg+xml", "svg"),
+    ("image/webp", "webp"),
+];
+
+pub struct EpubOptions {
+    pub title: String,
+    pub creator: Option<String>,
+    pub include_comments: bool,
+}
+
+/// Which posts to put in an EPUB. All set filters are combined.
+pub struct EpubSelection {
+    pub project: Option<String>,
+    pub liked_by: Option<String>,
+    pub tag: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ManifestItem {
+    id: String,
+    href: String,
+    media_type: String,
+    properties: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct NavItem {
+    href: String,
+    title: String,
+}
+
+struct EpubChapter {
+    published_at: String,
+    nav: NavItem,
+    items: Vec<ManifestItem>,
+}
+
+struct EpubImage {
+    href: String,
+}
+
+pub async fn export_epub(
+    db: &Database,
+    root_dir: &Path,
+    selection: EpubSelection,
+    title: Option<String>,
+    include_comments: bool,
+    out_path: &Path,
+) -> anyhow::Result<()> {
+    let mut query = PostQuery::default();
+    let mut title_parts = Vec::new();
+
+    if let Some(handle) = &selection.project {
+        let project = db
+            .project_id_for_handle(handle)
+            .await
+            .with_context(|| format!("could not find @{handle}"))?;
+        query.posting_project_id = Some(project);
+        title_parts.push(format!("posts by @{handle}"));
+    }
+    if let Some(handle) = &selection.liked_by {
+        let project = db
+            .project_id_for_handle(handle)
+            .await
+            .with_context(|| format!("could not find @{handle}"))?;
+        query.is_liked_by = Some(project);
+        title_parts.push(format!("liked by @{handle}"));
+    }
+    if let Some(tag) = &selection.tag {
+        query.include_tags.push(tag.clone());
+        title_parts.push(format!("tagged #{tag}"));
+    }
+
+    if title_parts.is_empty() {
+        anyhow::bail!("no posts selected (use --project, --liked-by, or --tag)");
+    }
+
+    let options = EpubOptions {
+        title: title.unwrap_or_else(|| title_parts.join(", ")),
+        creator: selection.project.map(|handle| format!("@{handle}")),
+        include_comments,
+    };
+
+    let renderer = PageRenderer::new();
+    renderer
+        .render_epub(db, root_dir, &query, &options, out_path)
+        .await?;
+
+    info!("exported to {}", out_path.display());
+
+    Ok(())
+}
+
+impl PageRenderer {
+    /// Renders all posts matching the query into an EPUB 3 file, in chronological order.
+    pub async fn render_epub(
+        &self,
+        db: &Database,
+        root_dir: &Path,
+        query: &PostQuery,
+        options: &EpubOptions,
+        out_path: &Path,
+    ) -> anyhow::Result<()> {
+        let file = File::create(out_path)
+            .with_context(|| format!("could not create {}", out_path.display()))?;
+        let mut zip = ZipWriter::new(file);
+
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // mimetype must be the first entry and must not be compressed
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(CONTAINER_XML.as_bytes())?;
+        zip.start_file("OEBPS/style.css", deflated)?;
+        zip.write_all(STYLE_CSS.as_bytes())?;
+
+        let mut query = query.clone();
+        query.offset = 0;
+        query.limit = 100;
+
+        let total = query.count(db).await?;
+        let progress = ProgressBar::new(total).with_style(long_progress_style());
+
+        let mut images = HashMap::new();
+        let mut image_items = Vec::new();
+        let mut chapters = Vec::new();
+        let mut id_hasher = Sha256::new();
+
+        loop {
+            let RenderedPosts {
+                posts,
+                rendered_posts,
+                ..
+            } = self.get_rendered_posts(db, 0, &query).await?;
+            if posts.is_empty() {
+                break;
+            }
+            query.offset += posts.len() as u64;
+
+            for post in posts {
+                progress.inc(1);
+                id_hasher.update(post.post_id.to_be_bytes());
+
+                let mut converter = XhtmlConverter::default();
+                let mut bodies = HashMap::new();
+                for post in std::iter::once(&post).chain(post.share_tree.iter()) {
+                    if let Some(rendered) = rendered_posts.get(&post.post_id) {
+                        let html = rendered.full.as_ref().unwrap_or(&rendered.preview);
+                        bodies.insert(post.post_id, converter.parse(html));
+                    }
+                }
+
+                let mut comments = HashMap::new();
+                let mut comment_bodies = HashMap::new();
+                if options.include_comments {
+                    comments = cohost_api_comments_for_share_tree(db, 0, &post).await?;
+                    for (id, rendered) in self.render_comments(db, &comments).await? {
+                        comment_bodies.insert(id, converter.parse(&rendered.html));
+                    }
+                }
+
+                for url in converter.image_urls() {
+                    if images.contains_key(&url) {
+                        continue;
+                    }
+                    let image =
+                        add_image(db, root_dir, &mut zip, deflated, &url, &mut image_items).await?;
+                    images.insert(url, image);
+                }
+
+                let bodies: HashMap<_, _> = bodies
+                    .into_iter()
+                    .map(|(id, doc)| (id, converter.serialize(&doc, &images)))
+                    .collect();
+                let comment_bodies: HashMap<_, _> = comment_bodies
+                    .into_iter()
+                    .map(|(id, doc)| (id, converter.serialize(&doc, &images)))
+                    .collect();
+
+                let chapter = self.render_epub_chapter(
+                    &mut zip,
+                    deflated,
+                    &post,
+                    bodies,
+                    comments,
+                    comment_bodies,
+                    converter.has_svg,
+                )?;
+                chapters.push(chapter);
+            }
+        }
+        progress.finish_and_clear();
+
+        // stable sort, so posts with equal dates keep their (reversed) order
+        chapters.reverse();
+        chapters.sort_by(|a, b| a.published_at.cmp(&b.published_at));
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("title", &options.title);
+        template_ctx.insert(
+            "nav_items",
+            &chapters.iter().map(|c| &c.nav).collect::<Vec<_>>(),
+        );
+        zip.start_file("OEBPS/nav.xhtml", deflated)?;
+        zip.write_all(self.tera.render("epub_nav.html", &template_ctx)?.as_bytes())?;
+
+        let mut manifest = Vec::new();
+        let mut spine = Vec::new();
+        for chapter in chapters {
+            for item in chapter.items {
+                spine.push(item.id.clone());
+                manifest.push(item);
+            }
+        }
+        manifest.extend(image_items);
+
+        let identifier = format!("urn:cohost-dl:{}", hex::encode(&id_hasher.finalize()[..16]));
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("identifier", &identifier);
+        template_ctx.insert("title", &options.title);
+        template_ctx.insert("creator", &options.creator);
+        template_ctx.insert(
+            "modified",
+            &Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        );
+        template_ctx.insert("manifest", &manifest);
+        template_ctx.insert("spine", &spine);
+        zip.start_file("OEBPS/package.opf", deflated)?;
+        zip.write_all(
+            self.tera
+                .render("epub_package.xml", &template_ctx)?
+                .as_bytes(),
+        )?;
+
+        zip.finish()?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_epub_chapter(
+        &self,
+        zip: &mut ZipWriter<File>,
+        options: SimpleFileOptions,
+        post: &PostFromCohost,
+        bodies: HashMap<u64, String>,
+        comments: HashMap<u64, Vec<CommentFromCohost>>,
+        comment_bodies: HashMap<String, String>,
+        has_svg: bool,
+    ) -> anyhow::Result<EpubChapter> {
+        let title = chapter_title(post);
+
+        // shared posts are shown in full as well
+        let mut cws = Vec::new();
+        let mut adult_content = false;
+        for post in post.share_tree.iter().chain(std::iter::once(post)) {
+            for cw in &post.cws {
+                if !cws.contains(cw) {
+                    cws.push(cw.clone());
+                }
+            }
+            adult_content |= post.effective_adult_content;
+        }
+        let has_cw = !cws.is_empty() || adult_content;
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("title", &title);
+        template_ctx.insert("cws", &cws);
+        template_ctx.insert("adult_content", &adult_content);
+        template_ctx.insert("post", post);
+        template_ctx.insert("bodies", &bodies);
+        template_ctx.insert("comments", &comments);
+        template_ctx.insert("comment_bodies", &comment_bodies);
+
+        let mut items = Vec::new();
+
+        let first_href = if has_cw {
+            let href = format!("posts/{}-cw.xhtml", post.post_id);
+            zip.start_file(format!("OEBPS/{href}"), options)?;
+            zip.write_all(self.tera.render("epub_cw.html", &template_ctx)?.as_bytes())?;
+            items.push(ManifestItem {
+                id: format!("post-{}-cw", post.post_id),
+                href: href.clone(),
+                media_type: "application/xhtml+xml".into(),
+                properties: None,
+            });
+            href
+        } else {
+            format!("posts/{}.xhtml", post.post_id)
+        };
+
+        let href = format!("posts/{}.xhtml", post.post_id);
+        zip.start_file(format!("OEBPS/{href}"), options)?;
+        zip.write_all(
+            self.tera
+                .render("epub_chapter.html", &template_ctx)?
+                .as_bytes(),
+        )?;
+        items.push(ManifestItem {
+            id: format!("post-{}", post.post_id),
+            href,
+            media_type: "application/xhtml+xml".into(),
+            properties: has_svg.then_some("svg"),
+        });
+
+        Ok(EpubChapter {
+            published_at: post.published_at.clone().unwrap_or_default(),
+            nav: NavItem {
+                href: first_href,
+                title,
+            },
+            items,
+        })
+    }
+}
+
+fn chapter_title(post: &PostFromCohost) -> String {
+    let date = post
+        .published_at
+        .as_deref()
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+
+    let mut title = post.headline.trim().to_string();
+    if title.is_empty() {
+        title = post
+            .plain_text_body
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some((index, _)) = title.char_indices().nth(60) {
+            title.truncate(index);
+            title.push('…');
+        }
+    }
+    if title.is_empty() {
+        title = format!("post by @{}", post.posting_project.handle);
+    }
+
+    format!("{date} — {title}")
+}
+
+async fn add_image(
+    db: &Database,
+    root_dir: &Path,
+    zip: &mut ZipWriter<File>,
+    options: SimpleFileOptions,
+    url: &Url,
+    items: &mut Vec<ManifestItem>,
+) -> anyhow::Result<Option<EpubImage>> {
+    let Some(path) = db.get_url_file(url).await? else {
+        return Ok(None);
+    };
+
+    let content_type = db.get_res_content_type(url).await?;
+    let Some((media_type, ext)) = EPUB_IMAGE_TYPES
+        .iter()
+        .find(|(ty, ext)| {
+            content_type.as_deref() == Some(ty)
+                || path
+                    .extension()
+                    .map_or(false, |e| e.eq_ignore_ascii_case(ext))
+        })
+        .copied()
+    else {
+        return Ok(None);
+    };
+
+    let data = match std::fs::read(root_dir.join(&path)) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("could not read {}: {e}", path.display());
+            return Ok(None);
+        }
+    };
+
+    let href = format!("images/{}.{ext}", items.len());
+    zip.start_file(format!("OEBPS/{href}"), options)?;
+    zip.write_all(&data)?;
+
+    items.push(ManifestItem {
+        id: format!("image-{}", items.len()),
+        href: href.clone(),
+        media_type: media_type.into(),
+        properties: None,
+    });
+
+    Ok(Some(EpubImage {
+        href: format!("../{href}"),
+    }))
+}
+
+/// Converts renderer HTML to something that is valid XHTML and reasonable to put in an EPUB.
+#[derive(Default)]
+struct XhtmlConverter {
+    image_urls: HashSet<Url>,
+    has_svg: bool,
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Interactive or external content. These are dropped entirely.
+const DROPPED_ELEMENTS: &[&str] = &[
+    "base", "button", "embed", "form", "iframe", "input", "link", "meta", "noscript", "object",
+    "script", "select", "style", "template", "textarea",
+];
+
+impl XhtmlConverter {
+    fn parse(&mut self, html: &str) -> NodeRef {
+        let doc = kuchikiki::parse_html().one(html);
+
+        for img in doc.select("img").into_iter().flatten() {
+            let attrs = img.attributes.borrow();
+            if let Some(url) = attrs.get("src").and_then(resolve_resource_url) {
+                self.image_urls.insert(url);
+            }
+        }
+
+        doc
+    }
+
+    fn image_urls(&self) -> Vec<Url> {
+        self.image_urls.iter().cloned().collect()
+    }
+
+    fn serialize(&mut self, doc: &NodeRef, images: &HashMap<Url, Option<EpubImage>>) -> String {
+        let mut out = String::new();
+        if let Ok(body) = doc.select_first("body") {
+            for child in body.as_node().children() {
+                self.write_node(&child, images, &mut out);
+            }
+        }
+        out
+    }
+
+    fn write_node(
+        &mut self,
+        node: &NodeRef,
+        images: &HashMap<Url, Option<EpubImage>>,
+        out: &mut String,
+    ) {
+        match node.data() {
+            NodeData::Text(text) => write_escaped(&text.borrow(), false, out),
+            NodeData::Element(element) => {
+                let name = &*element.name.local;
+                let is_svg = element.name.ns == ns!(svg);
+                let is_html = element.name.ns == ns!(html);
+
+                if is_html && DROPPED_ELEMENTS.contains(&name) {
+                    return;
+                }
+                if !is_xml_name(name) {
+                    for child in node.children() {
+                        self.write_node(&child, images, out);
+                    }
+                    return;
+                }
+
+                let attrs = element.attributes.borrow();
+
+                if is_html && matches!(name, "audio" | "video") {
+                    let src = attrs.get("src").map(|s| s.to_string()).or_else(|| {
+                        node.select_first("source")
+                            .ok()
+                            .and_then(|s| s.attributes.borrow().get("src").map(|s| s.to_string()))
+                    });
+                    if let Some(src) = src {
+                        self.write_link(&src, &format!("[{name}]"), out);
+                    }
+                    return;
+                }
+
+                if is_html && name == "img" {
+                    let src = attrs.get("src").unwrap_or_default();
+                    let embedded = resolve_resource_url(src)
+                        .and_then(|url| images.get(&url))
+                        .and_then(|image| image.as_ref());
+
+                    if let Some(image) = embedded {
+                        out.push_str("<img src=\"");
+                        write_escaped(&image.href, true, out);
+                        out.push_str("\" alt=\"");
+                        write_escaped(attrs.get("alt").unwrap_or_default(), true, out);
+                        out.push('"');
+                        if let Some(title) = attrs.get("title") {
+                            out.push_str(" title=\"");
+                            write_escaped(title, true, out);
+                            out.push('"');
+                        }
+                        out.push_str(" />");
+                    } else {
+                        let alt = attrs.get("alt").filter(|alt| !alt.is_empty());
+                        let label = match alt {
+                            Some(alt) => format!("[image: {alt}]"),
+                            None => "[image]".into(),
+                        };
+                        self.write_link(src, &label, out);
+                    }
+                    return;
+                }
+
+                out.push('<');
+                out.push_str(name);
+
+                let parent_is_svg = node.parent().map_or(false, |parent| {
+                    parent
+                        .as_element()
+                        .map_or(false, |parent| parent.name.ns == ns!(svg))
+                });
+                if is_svg && !parent_is_svg {
+                    self.has_svg = true;
+                    out.push_str(" xmlns=\"http://www.w3.org/2000/svg\"");
+                }
+
+                for (attr_name, attr) in &attrs.map {
+                    let attr_name = &*attr_name.local;
+                    if !is_xml_name(attr_name)
+                        || attr_name.starts_with("on")
+                        || attr_name == "xmlns"
+                        || attr_name == "srcset"
+                    {
+                        continue;
+                    }
+
+                    let value = if is_html && attr_name == "href" {
+                        absolute_link(&attr.value)
+                    } else {
+                        attr.value.clone()
+                    };
+
+                    out.push(' ');
+                    out.push_str(attr_name);
+                    out.push_str("=\"");
+                    write_escaped(&value, true, out);
+                    out.push('"');
+                }
+
+                if is_html && VOID_ELEMENTS.contains(&name) {
+                    out.push_str(" />");
+                    return;
+                }
+                out.push('>');
+
+                for child in node.children() {
+                    self.write_node(&child, images, out);
+                }
+
+                out.push_str("</");
+                out.push_str(name);
+                out.push('>');
+            }
+            _ => (),
+        }
+    }
+
+    fn write_link(&self, href: &str, label: &str, out: &mut String) {
+        out.push_str("<a href=\"");
+        write_escaped(&absolute_link(href), true, out);
+        out.push_str("\">");
+        write_escaped(label, false, out);
+        out.push_str("</a>");
+    }
+}
+
+/// Resolves links to local routes to their original location.
+fn absolute_link(href: &str) -> String {
+    if let Some(url) = resolve_resource_url(href) {
+        return url.to_string();
+    }
+    if href.starts_with('/') && !href.starts_with("//") {
+        return format!("https://cohost.org{href}");
+    }
+    href.to_string()
+}
+
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn write_escaped(s: &str, attr: bool, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            // not allowed in XML 1.0
+            '\u{0}'..='\u{8}'
+            | '\u{b}'
+            | '\u{c}'
+            | '\u{e}'..='\u{1f}'
+            | '\u{fffe}'
+            | '\u{ffff}' => {}
+            c => out.push(c),
+        }
+    }
+}
+
+#[test]
+fn test_xhtml_converter() {
+    assert!(is_xml_name("data-x.y_1"));
+    assert!(!is_xml_name(""));
+    assert!(!is_xml_name("1a"));
+    assert!(!is_xml_name("my:tag"));
+    assert!(!is_xml_name("a\"b"));
+
+    let mut out = String::new();
+    write_escaped("a < b & \"c\" > d\u{1}\u{fffe}", true, &mut out);
+    assert_eq!(out, "a &lt; b &amp; &quot;c&quot; &gt; d");
+    let mut out = String::new();
+    write_escaped("\"c\" & d", false, &mut out);
+    assert_eq!(out, "\"c\" &amp; d");
+
+    assert_eq!(
+        absolute_link("/r/https/example.com/a.png"),
+        "https://example.com/a.png"
+    );
+    assert_eq!(
+        absolute_link("/foo/post/1-a"),
+        "https://cohost.org/foo/post/1-a"
+    );
+    assert_eq!(absolute_link("//example.com/a"), "//example.com/a");
+    assert_eq!(absolute_link("#footnote"), "#footnote");
+
+    let mut converter = XhtmlConverter::default();
+    let doc = converter.parse(concat!(
+        r#"<p a"b="1" onclick="x()" title="&quot;<">one &amp; <br>two</p><hr>"#,
+        r#"<img src="/r/https/example.com/a.png" alt="cat"><img src="/r/https/example.com/b.png">"#,
+        r#"<script>evil()</script><my:tag>kept</my:tag><a href="/foo/post/1-a">x</a>"#,
+    ));
+
+    let a_png = Url::parse("https://example.com/a.png").unwrap();
+    let b_png = Url::parse("https://example.com/b.png").unwrap();
+    let mut image_urls = converter.image_urls();
+    image_urls.sort();
+    assert_eq!(image_urls, vec![a_png.clone(), b_png.clone()]);
+
+    let mut images = HashMap::new();
+    images.insert(
+        a_png,
+        Some(EpubImage {
+            href: "../images/0.png".into(),
+        }),
+    );
+    // images that could not be embedded become links
+    images.insert(b_png, None);
+
+    assert_eq!(
+        converter.serialize(&doc, &images),
+        "<p title=\"&quot;&lt;\">one &amp; <br />two</p><hr />\
+        <img src=\"../images/0.png\" alt=\"cat\" />\
+        <a href=\"https://example.com/b.png\">[image]</a>\
+        kept<a href=\"https://cohost.org/foo/post/1-a\">x</a>"
+    );
+}
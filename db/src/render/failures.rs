@@ -0,0 +1,48 @@
+use crate::data::Database;
+use crate::failures::{group_failures, load_failures, FailureFilter, FailureGrouping};
+use crate::render::PageRenderer;
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+/// Query parameters for the failure report. Empty fields (as sent by the form) are ignored.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailuresQuery {
+    group_by: FailureGrouping,
+    host: String,
+    status: String,
+    class: String,
+}
+
+impl FailuresQuery {
+    fn filter(&self) -> FailureFilter {
+        let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+
+        FailureFilter {
+            host: non_empty(&self.host),
+            status: self.status.trim().parse().ok(),
+            class: non_empty(&self.class),
+        }
+    }
+}
+
+impl PageRenderer {
+    pub async fn render_failures_page(
+        &self,
+        db: &Database,
+        query: &FailuresQuery,
+    ) -> anyhow::Result<String> {
+        let failures = load_failures(db, &query.filter()).await?;
+        let total = failures.len();
+        let groups = group_failures(failures, query.group_by);
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("query", query);
+        template_ctx.insert("total", &total);
+        template_ctx.insert("groups", &groups);
+
+        let body = self.tera.render("failures.html", &template_ctx)?;
+
+        Ok(body)
+    }
+}
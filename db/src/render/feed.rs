@@ -1,10 +1,11 @@
 use crate::data::{Database, PostQuery};
 use crate::post::PostFromCohost;
 use crate::render::api_data::{cohost_api_post, cohost_api_project, GetDataError};
+use crate::render::archive::{archive_base, MONTH_NAMES};
 use crate::render::md_render::{PostRenderRequest, PostRenderResult};
-use crate::render::rewrite::rewrite_projects_in_post;
+use crate::render::rewrite::{archived_cohost_links, rewrite_projects_in_post};
 use crate::render::PageRenderer;
-use chrono::Utc;
+use chrono::{Datelike, NaiveDate, Utc};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -14,6 +15,8 @@ use thiserror::Error;
 pub struct RenderedPosts {
     pub posts: Vec<PostFromCohost>,
     pub rendered_posts: HashMap<u64, PostRenderResult>,
+    /// Number of archived shares of each post
+    pub share_counts: HashMap<u64, u64>,
     pub max_page: u64,
 }
 
@@ -34,10 +37,18 @@ impl PageRenderer {
         let mut rendered_posts = HashMap::with_capacity(post_ids.len());
 
         for post in post_ids {
-            let mut post = cohost_api_post(db, viewer_id, post).await?;
+            let mut post = match cohost_api_post(db, viewer_id, post).await {
+                Ok(post) => post,
+                // something in the share tree is hidden
+                Err(GetDataError::NotFound) => continue,
+                Err(e) => return Err(e),
+            };
 
             for post in std::iter::once(&post).chain(post.share_tree.iter()) {
                 let resources = db.get_saved_resource_urls_for_post(post.post_id).await?;
+                let links = archived_cohost_links(db, &post.blocks)
+                    .await
+                    .map_err(GetDataError::Render)?;
 
                 let result = self
                     .md
@@ -50,6 +61,8 @@ impl PageRenderer {
                             .unwrap_or_else(|| Utc::now().to_rfc3339()),
                         has_cohost_plus: post.has_cohost_plus,
                         resources,
+                        links,
+                        wayback_links: self.options.wayback_links,
                     })
                     .await
                     .map_err(|e| GetDataError::Render(e))?;
@@ -64,9 +77,13 @@ impl PageRenderer {
             posts.push(post);
         }
 
+        let post_ids: Vec<_> = posts.iter().map(|post| post.post_id).collect();
+        let share_counts = db.share_counts(&post_ids).await?;
+
         Ok(RenderedPosts {
             posts,
             rendered_posts,
+            share_counts,
             max_page,
         })
     }
@@ -207,6 +224,7 @@ impl PageRenderer {
         let RenderedPosts {
             posts,
             rendered_posts,
+            share_counts,
             max_page,
         } = self.get_rendered_posts(db, 0, &post_query).await?;
 
@@ -218,6 +236,7 @@ impl PageRenderer {
 
         template_ctx.insert("posts", &posts);
         template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
 
         template_ctx.insert("filter_state", &query.to_filter_state(path, max_page));
 
@@ -250,6 +269,7 @@ impl PageRenderer {
         let RenderedPosts {
             posts,
             rendered_posts,
+            share_counts,
             max_page,
         } = self.get_rendered_posts(db, project_id, &post_query).await?;
 
@@ -258,6 +278,7 @@ impl PageRenderer {
 
         template_ctx.insert("posts", &posts);
         template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
 
         let path = format!("/{}/liked-posts", project.handle);
         template_ctx.insert("filter_state", &query.to_filter_state(&path, max_page));
@@ -291,6 +312,7 @@ impl PageRenderer {
         let RenderedPosts {
             posts,
             rendered_posts,
+            share_counts,
             max_page,
         } = self.get_rendered_posts(db, project_id, &post_query).await?;
 
@@ -299,6 +321,7 @@ impl PageRenderer {
 
         template_ctx.insert("posts", &posts);
         template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
 
         let path = format!("/{}/dashboard", project.handle);
         template_ctx.insert("filter_state", &query.to_filter_state(&path, max_page));
@@ -307,4 +330,188 @@ impl PageRenderer {
 
         Ok(body)
     }
+
+    /// Renders asks the project answered, or non-anonymous asks it sent to others if `sent`.
+    pub async fn render_asks(
+        &self,
+        db: &Database,
+        project: &str,
+        sent: bool,
+        query: TagFeedQuery,
+    ) -> Result<String, RenderFeedError> {
+        // people who only sent asks can be found without having been archived themselves
+        let project_id = match db.project_id_for_handle(project).await {
+            Err(diesel::result::Error::NotFound) if sent => {
+                db.asking_project_id_for_handle(project).await
+            }
+            result => result,
+        }
+        .map_err(GetDataError::from)?;
+
+        let summary = db
+            .ask_summary(project_id)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let post_query = PostQuery {
+            offset: query.page * 20,
+            limit: 20,
+            posting_project_id: if sent { None } else { Some(project_id) },
+            is_ask: if sent { None } else { Some(true) },
+            asked_by: if sent { Some(project_id) } else { None },
+            is_adult: match query.show_18_plus_posts {
+                true => None,
+                false => Some(false),
+            },
+            ..Default::default()
+        };
+
+        let RenderedPosts {
+            posts,
+            rendered_posts,
+            share_counts,
+            max_page,
+        } = self.get_rendered_posts(db, 0, &post_query).await?;
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", project);
+        template_ctx.insert("sent", &sent);
+        template_ctx.insert("summary", &summary);
+
+        template_ctx.insert("posts", &posts);
+        template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
+
+        let path = if sent {
+            format!("/{project}/asks/sent")
+        } else {
+            format!("/{project}/asks")
+        };
+        template_ctx.insert("filter_state", &query.to_filter_state(&path, max_page));
+
+        let body = self.tera.render("asks_feed.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    /// Renders posts by other projects that mention the project, or link to its posts if `links`.
+    pub async fn render_backlinks(
+        &self,
+        db: &Database,
+        project: &str,
+        links: bool,
+        query: TagFeedQuery,
+    ) -> Result<String, RenderFeedError> {
+        let project_id = db
+            .project_id_for_handle(project)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let mention_count = PostQuery {
+            mentions_project: Some(project_id),
+            ..Default::default()
+        }
+        .count(db)
+        .await
+        .map_err(GetDataError::from)?;
+        let link_count = PostQuery {
+            links_to_project: Some(project_id),
+            ..Default::default()
+        }
+        .count(db)
+        .await
+        .map_err(GetDataError::from)?;
+
+        let post_query = PostQuery {
+            offset: query.page * 20,
+            limit: 20,
+            mentions_project: if links { None } else { Some(project_id) },
+            links_to_project: if links { Some(project_id) } else { None },
+            is_adult: match query.show_18_plus_posts {
+                true => None,
+                false => Some(false),
+            },
+            ..Default::default()
+        };
+
+        let RenderedPosts {
+            posts,
+            rendered_posts,
+            share_counts,
+            max_page,
+        } = self.get_rendered_posts(db, 0, &post_query).await?;
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", project);
+        template_ctx.insert("links", &links);
+        template_ctx.insert("mention_count", &mention_count);
+        template_ctx.insert("link_count", &link_count);
+
+        template_ctx.insert("posts", &posts);
+        template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
+
+        let path = if links {
+            format!("/{project}/linked-from")
+        } else {
+            format!("/{project}/mentions")
+        };
+        template_ctx.insert("filter_state", &query.to_filter_state(&path, max_page));
+
+        let body = self.tera.render("backlinks_feed.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    /// Renders all posts published on one day (in UTC).
+    pub async fn render_archive_day(
+        &self,
+        db: &Database,
+        project: Option<&str>,
+        (year, month, day): (i32, u32, u32),
+        query: TagFeedQuery,
+    ) -> Result<String, RenderFeedError> {
+        let date = NaiveDate::from_ymd_opt(year, month, day).ok_or(GetDataError::NotFound)?;
+        let next_date = date.succ_opt().ok_or(GetDataError::NotFound)?;
+        let (base, project_id) = archive_base(db, project).await?;
+
+        let post_query = PostQuery {
+            offset: query.page * 20,
+            limit: 20,
+            posting_project_id: project_id,
+            published_after: Some(date.format("%Y-%m-%d").to_string()),
+            published_before: Some(next_date.format("%Y-%m-%d").to_string()),
+            is_adult: match query.show_18_plus_posts {
+                true => None,
+                false => Some(false),
+            },
+            ..Default::default()
+        };
+
+        let RenderedPosts {
+            posts,
+            rendered_posts,
+            share_counts,
+            max_page,
+        } = self.get_rendered_posts(db, 0, &post_query).await?;
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", &project);
+        template_ctx.insert("base", &base);
+        template_ctx.insert("year", &date.year());
+        template_ctx.insert("month", &date.month());
+        template_ctx.insert("month_name", MONTH_NAMES[date.month0() as usize]);
+        template_ctx.insert("day", &date.day());
+
+        template_ctx.insert("posts", &posts);
+        template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
+
+        let path = format!("{base}/{year}/{month}/{day}");
+        template_ctx.insert("filter_state", &query.to_filter_state(&path, max_page));
+
+        let body = self.tera.render("archive_day.html", &template_ctx)?;
+
+        Ok(body)
+    }
 }
@@ -6,7 +6,7 @@ use deno_core::{ascii_str, serde_v8, v8, JsRuntime, RuntimeOptions};
 use deno_web::TimersPermission;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Condvar, Mutex};
 use tokio::sync::oneshot;
 
@@ -18,6 +18,10 @@ pub struct PostRenderRequest {
     pub published_at: String,
     pub has_cohost_plus: bool,
     pub resources: Vec<String>,
+    /// Local URLs for archived things linked on cohost.org (see [crate::render::rewrite::archived_cohost_links])
+    pub links: HashMap<String, String>,
+    /// Add a Wayback Machine link next to links to cohost.org that aren't archived
+    pub wayback_links: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,7 +52,7 @@ pub enum MarkdownRenderContext {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MarkdownRenderResult {
-    html: String,
+    pub html: String,
 }
 
 enum QueueItem {
@@ -3,20 +3,41 @@ use crate::render::md_render::MarkdownRenderer;
 use tera::{Context, Tera};
 
 pub mod api_data;
+pub mod archive;
+pub mod epub;
+pub mod failures;
 pub mod feed;
 pub mod index;
 pub mod md_render;
+pub mod project_comments;
 pub mod project_profile;
+pub mod projects;
 pub mod rewrite;
+pub mod shares;
 pub mod single_post;
+pub mod tags;
+
+/// Settings that change how pages are rendered.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Add a Wayback Machine link next to links to cohost.org that aren't archived
+    pub wayback_links: bool,
+    /// Leave out pages that only make sense for the person who made the archive
+    pub public_mirror: bool,
+}
 
 pub struct PageRenderer {
     tera: Tera,
     md: MarkdownRenderer,
+    options: RenderOptions,
 }
 
 impl PageRenderer {
     pub fn new() -> Self {
+        Self::with_options(RenderOptions::default())
+    }
+
+    pub fn with_options(options: RenderOptions) -> Self {
         let mut tera = Tera::default();
 
         #[rustfmt::skip]
@@ -29,7 +50,7 @@ impl PageRenderer {
 
         let md = MarkdownRenderer::new(4);
 
-        Self { tera, md }
+        Self { tera, md, options }
     }
 
     pub fn render_error_page(&self, message: &str) -> String {
@@ -0,0 +1,142 @@
+use crate::comment::CommentFromCohost;
+use crate::data::Database;
+use crate::render::api_data::{cohost_api_project_comments, CommentInContext, GetDataError};
+use crate::render::feed::RenderFeedError;
+use crate::render::md_render::{MarkdownRenderContext, MarkdownRenderRequest};
+use crate::render::rewrite::{rewrite_projects_in_comment, rewrite_projects_in_post};
+use crate::render::PageRenderer;
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+pub const COMMENTS_PER_PAGE: u64 = 20;
+const PREVIEW_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectCommentsQuery {
+    #[serde(default)]
+    pub page: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CommentListItem {
+    #[serde(flatten)]
+    item: CommentInContext,
+    html: String,
+    post_preview: String,
+    in_reply_to_preview: Option<String>,
+}
+
+/// Shortens text to a single line of at most [PREVIEW_LENGTH] characters.
+fn preview_text(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() > PREVIEW_LENGTH {
+        let text: String = text.chars().take(PREVIEW_LENGTH).collect();
+        format!("{}…", text.trim_end())
+    } else {
+        text
+    }
+}
+
+fn comment_preview(comment: &CommentFromCohost) -> String {
+    if comment.comment.deleted {
+        "(deleted)".into()
+    } else {
+        preview_text(&comment.comment.body)
+    }
+}
+
+impl PageRenderer {
+    pub async fn render_project_comments(
+        &self,
+        db: &Database,
+        project: &str,
+        query: ProjectCommentsQuery,
+    ) -> Result<String, RenderFeedError> {
+        let project_id = db
+            .project_id_for_handle(project)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let total = db
+            .project_comment_count(project_id)
+            .await
+            .map_err(GetDataError::from)?;
+        let items = cohost_api_project_comments(
+            db,
+            0,
+            project_id,
+            query.page * COMMENTS_PER_PAGE,
+            COMMENTS_PER_PAGE,
+        )
+        .await?;
+
+        let mut comments = Vec::with_capacity(items.len());
+        for mut item in items {
+            rewrite_projects_in_post(db, &mut item.post)
+                .await
+                .map_err(GetDataError::Render)?;
+            rewrite_projects_in_comment(db, &mut item.comment)
+                .await
+                .map_err(GetDataError::Render)?;
+            if let Some(in_reply_to) = &mut item.in_reply_to {
+                rewrite_projects_in_comment(db, in_reply_to)
+                    .await
+                    .map_err(GetDataError::Render)?;
+            }
+
+            let resources = db
+                .get_saved_resource_urls_for_comment(&item.comment.comment.comment_id)
+                .await
+                .map_err(|e| GetDataError::Render(e.into()))?;
+
+            let rendered = self
+                .md
+                .render_markdown(MarkdownRenderRequest {
+                    markdown: item.comment.comment.body.clone(),
+                    context: MarkdownRenderContext::Comment,
+                    published_at: item.comment.comment.posted_at_iso.clone(),
+                    has_cohost_plus: item.comment.comment.has_cohost_plus,
+                    resources,
+                })
+                .await
+                .map_err(GetDataError::Render)?;
+
+            let post_preview = match item.post.headline.trim() {
+                "" => preview_text(&item.post.plain_text_body),
+                headline => preview_text(headline),
+            };
+            let in_reply_to_preview = item.in_reply_to.as_ref().map(comment_preview);
+
+            comments.push(CommentListItem {
+                item,
+                html: rendered.html,
+                post_preview,
+                in_reply_to_preview,
+            });
+        }
+
+        let max_page = total.saturating_sub(1) / COMMENTS_PER_PAGE;
+        let prev_page = if query.page > 0 {
+            format!("?page={}", query.page - 1)
+        } else {
+            "".into()
+        };
+        let next_page = if query.page < max_page {
+            format!("?page={}", query.page + 1)
+        } else {
+            "".into()
+        };
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", project);
+        template_ctx.insert("total", &total);
+        template_ctx.insert("comments", &comments);
+        template_ctx.insert("prev_page", &prev_page);
+        template_ctx.insert("next_page", &next_page);
+
+        let body = self.tera.render("project_comments.html", &template_ctx)?;
+
+        Ok(body)
+    }
+}
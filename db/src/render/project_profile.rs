@@ -174,20 +174,28 @@ impl PageRenderer {
         let RenderedPosts {
             posts,
             rendered_posts,
+            share_counts,
             max_page,
         } = self
             .get_rendered_posts(db, 0, &post_query)
             .await
             .map_err(|e| RenderProjectProfileError::Unknown(e.into()))?;
 
+        let backlinks = db
+            .project_backlinks(project_id, 5)
+            .await
+            .map_err(|e| RenderProjectProfileError::Unknown(e.into()))?;
+
         let mut template_ctx = Context::new();
         template_ctx.insert("project", &project);
         template_ctx.insert(
             "rendered_project_description",
             &rendered_project_description,
         );
+        template_ctx.insert("backlinks", &backlinks);
         template_ctx.insert("posts", &posts);
         template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
         template_ctx.insert("filter_state", &query.to_filter_state(max_page));
 
         if let Some(tag) = tagged {
@@ -0,0 +1,167 @@
+use crate::data::{Database, ProjectListing, ProjectQuery, ProjectSort};
+use crate::project::ProjectFromCohost;
+use crate::render::api_data::{cohost_api_project, GetDataError};
+use crate::render::feed::RenderFeedError;
+use crate::render::rewrite::rewrite_project;
+use crate::render::PageRenderer;
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const PROJECTS_PER_PAGE: u64 = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectDirectoryQuery {
+    #[serde(default)]
+    page: u64,
+    /// Only show projects whose handle contains this
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    sort: ProjectSort,
+}
+
+impl ProjectDirectoryQuery {
+    fn fmt_query(&self) -> String {
+        let mut out = Vec::new();
+
+        if self.page > 0 {
+            out.push(format!("page={}", self.page));
+        }
+        if !self.q.is_empty() {
+            out.push(format!("q={}", urlencoding::encode(&self.q)));
+        }
+        if self.sort != ProjectSort::default() {
+            let sort = serde_json::to_value(self.sort).unwrap_or_default();
+            out.push(format!("sort={}", sort.as_str().unwrap_or_default()));
+        }
+
+        let mut out = out.join("&");
+        if !out.is_empty() {
+            out.insert(0, '?');
+        }
+        out
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DirectoryEntry {
+    #[serde(flatten)]
+    listing: ProjectListing,
+    project: ProjectFromCohost,
+}
+
+/// Which follows to list for a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowList {
+    Following,
+    Followers,
+}
+
+impl PageRenderer {
+    async fn render_directory(
+        &self,
+        db: &Database,
+        mut template_ctx: Context,
+        project_query: ProjectQuery,
+        query: ProjectDirectoryQuery,
+    ) -> Result<String, RenderFeedError> {
+        let project_query = ProjectQuery {
+            search: query.q.trim().to_string(),
+            sort: query.sort,
+            offset: query.page * PROJECTS_PER_PAGE,
+            limit: PROJECTS_PER_PAGE,
+            ..project_query
+        };
+
+        let listings = project_query.get(db).await.map_err(GetDataError::from)?;
+        let total = project_query.count(db).await.map_err(GetDataError::from)?;
+
+        let mut entries = Vec::with_capacity(listings.len());
+        for listing in listings {
+            let mut project = cohost_api_project(db, 0, listing.id).await?;
+            rewrite_project(db, &mut project)
+                .await
+                .map_err(GetDataError::Render)?;
+
+            entries.push(DirectoryEntry { listing, project });
+        }
+
+        let max_page = total.saturating_sub(1) / PROJECTS_PER_PAGE;
+        let prev_page = if query.page > 0 {
+            ProjectDirectoryQuery {
+                page: query.page - 1,
+                ..query.clone()
+            }
+            .fmt_query()
+        } else {
+            "".into()
+        };
+        let next_page = if query.page < max_page {
+            ProjectDirectoryQuery {
+                page: query.page + 1,
+                ..query.clone()
+            }
+            .fmt_query()
+        } else {
+            "".into()
+        };
+
+        template_ctx.insert("query", &query);
+        template_ctx.insert("total", &total);
+        template_ctx.insert("entries", &entries);
+        template_ctx.insert("prev_page", &prev_page);
+        template_ctx.insert("next_page", &next_page);
+
+        let body = self.tera.render("project_directory.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    /// Renders a list of all archived projects.
+    pub async fn render_project_directory(
+        &self,
+        db: &Database,
+        query: ProjectDirectoryQuery,
+    ) -> Result<String, RenderFeedError> {
+        self.render_directory(db, Context::new(), ProjectQuery::default(), query)
+            .await
+    }
+
+    /// Renders a list of the archived projects that a project follows, or that follow it.
+    pub async fn render_project_follows(
+        &self,
+        db: &Database,
+        project: &str,
+        list: FollowList,
+        query: ProjectDirectoryQuery,
+    ) -> Result<String, RenderFeedError> {
+        let project_id = db
+            .project_id_for_handle(project)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let project_query = match list {
+            FollowList::Following => ProjectQuery {
+                followed_by: Some(project_id),
+                ..Default::default()
+            },
+            FollowList::Followers => ProjectQuery {
+                following: Some(project_id),
+                ..Default::default()
+            },
+        };
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", project);
+        template_ctx.insert(
+            "list",
+            match list {
+                FollowList::Following => "following",
+                FollowList::Followers => "followers",
+            },
+        );
+
+        self.render_directory(db, template_ctx, project_query, query)
+            .await
+    }
+}
@@ -1,8 +1,19 @@
+use crate::bundled_files::CDL_STATIC;
 use crate::comment::CommentFromCohost;
 use crate::data::Database;
-use crate::post::PostFromCohost;
+use crate::link_ref::LinkRefs;
+use crate::post::{PostBlock, PostFromCohost};
 use crate::project::ProjectFromCohost;
+use crate::render::single_post::parse_post_id;
+use crate::res_ref::srcset_candidates;
+use crate::server::content_type_for_ext;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use deno_core::url::Url;
+use html5ever::tendril::TendrilSink;
+use html5ever::{local_name, namespace_url, ns, QualName};
+use kuchikiki::{Attribute, ExpandedName, NodeRef};
+use std::collections::{HashMap, HashSet};
 
 fn make_resource_url(s: &str) -> String {
     if let Ok(url) = Url::parse(s) {
@@ -29,6 +40,32 @@ fn make_resource_url(s: &str) -> String {
     format!("/r/u?url={}", urlencoding::encode(s))
 }
 
+/// Inverse of [make_resource_url]: returns the original URL for a local resource URL.
+pub fn resolve_resource_url(s: &str) -> Option<Url> {
+    if !s.starts_with("/r/") && !s.starts_with("/r?") {
+        return None;
+    }
+    let local = Url::parse("http://localhost").ok()?.join(s).ok()?;
+    let query: HashMap<_, _> = local.query_pairs().into_owned().collect();
+
+    let segments: Vec<_> = local.path().split('/').skip(2).collect();
+    if segments.len() < 2 {
+        return query.get("url").and_then(|url| Url::parse(url).ok());
+    }
+
+    let path = segments[2..]
+        .iter()
+        .fold(String::new(), |acc, i| acc + "/" + i);
+    let mut url = Url::parse(&format!("{}://{}{}", segments[0], segments[1], path)).ok()?;
+    if let Some(q) = query.get("q") {
+        url.set_query(Some(q));
+    }
+    if let Some(h) = query.get("h") {
+        url.set_fragment(Some(h));
+    }
+    Some(url)
+}
+
 pub async fn rewrite_project(db: &Database, project: &mut ProjectFromCohost) -> anyhow::Result<()> {
     let resources = db
         .get_saved_resource_urls_for_project(project.project_id)
@@ -83,3 +120,394 @@ pub async fn rewrite_projects_in_comment(
 
     Ok(())
 }
+
+/// Returns the local URL for a link to cohost.org, if what it links to is in the archive.
+async fn local_cohost_url(db: &Database, url: &Url) -> anyhow::Result<Option<String>> {
+    let segments: Vec<_> = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    let has_project = |handle: &str| {
+        let handle = handle.to_string();
+        async move {
+            match db.project_id_for_handle(&handle).await {
+                Ok(_) => Ok(true),
+                Err(diesel::result::Error::NotFound) => Ok(false),
+                Err(e) => Err(e),
+            }
+        }
+    };
+
+    let local = match segments.as_slice() {
+        ["rc", "tagged", tag] => Some(format!("/rc/tagged/{tag}")),
+        ["rc" | "static" | "api", ..] => None,
+        [handle] => has_project(handle).await?.then(|| format!("/{handle}")),
+        [handle, "tagged", tag] => has_project(handle)
+            .await?
+            .then(|| format!("/{handle}/tagged/{tag}")),
+        [_, "post", post] => match parse_post_id(post) {
+            // the handle in the link may be outdated
+            Ok(post) => db
+                .post_location(post)
+                .await?
+                .map(|(handle, filename)| format!("/{handle}/post/{filename}")),
+            Err(_) => None,
+        },
+        _ => None,
+    };
+
+    Ok(local)
+}
+
+/// Finds links to cohost.org in a post, and returns local URLs for the ones that are archived.
+///
+/// Keys are `https://cohost.org` followed by the path and query of the link.
+pub async fn archived_cohost_links(
+    db: &Database,
+    blocks: &[PostBlock],
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut urls = HashSet::new();
+    for block in blocks {
+        let links = match block {
+            PostBlock::Markdown { markdown } => markdown.collect_links(),
+            PostBlock::Ask { ask } => ask.content.collect_links(),
+            _ => continue,
+        };
+        urls.extend(links.urls);
+        urls.extend(
+            links
+                .mentions
+                .into_iter()
+                .map(|handle| format!("https://cohost.org/{handle}")),
+        );
+    }
+
+    let mut archived = HashMap::new();
+    for url in urls {
+        let Ok(url) = Url::parse(&url) else {
+            continue;
+        };
+        let key = match url.query() {
+            Some(query) => format!("https://cohost.org{}?{query}", url.path()),
+            None => format!("https://cohost.org{}", url.path()),
+        };
+        if archived.contains_key(&key) {
+            continue;
+        }
+
+        if let Some(local) = local_cohost_url(db, &url).await? {
+            archived.insert(key, local);
+        }
+    }
+
+    Ok(archived)
+}
+
+/// Stylesheets that are inlined into standalone pages.
+const INLINED_STYLESHEETS: &[&str] = &["base.css", "tailwind-prose.css"];
+
+fn inlined_stylesheet(name: &str) -> Option<String> {
+    if !INLINED_STYLESHEETS.contains(&name) {
+        return None;
+    }
+    CDL_STATIC
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, contents)| String::from_utf8_lossy(contents).into_owned())
+}
+
+/// Calls `f` with every URL in the srcset, and rebuilds it with the results.
+/// Image candidates for which `f` returns None are left out.
+fn map_srcset(srcset: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = Vec::new();
+    for (url, descriptor) in srcset_candidates(srcset) {
+        if let Some(url) = f(&url) {
+            if descriptor.is_empty() {
+                out.push(url);
+            } else {
+                out.push(format!("{url} {descriptor}"));
+            }
+        }
+    }
+    out.join(", ")
+}
+
+#[test]
+fn test_map_srcset() {
+    let srcset = "https://a.com/a.png 1x, https://a.com/a.png?w=2 2x,https://b.com/b.png";
+    assert_eq!(
+        map_srcset(srcset, |url| Some(format!("/r?url={url}"))),
+        "/r?url=https://a.com/a.png 1x, /r?url=https://a.com/a.png?w=2 2x, /r?url=https://b.com/b.png"
+    );
+    assert_eq!(
+        map_srcset(srcset, |url| url.contains('?').then(|| "x.png".to_string())),
+        "x.png 2x"
+    );
+    assert_eq!(
+        map_srcset(", a.png 100w", |url| Some(url.into())),
+        "a.png 100w"
+    );
+}
+
+/// Calls `f` with the resolved URL of every `url(...)` in the CSS, and replaces it with the result.
+fn map_css_urls(css: &str, base: &Url, mut f: impl FnMut(Url) -> Option<String>) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(index) = rest.find("url(") {
+        out.push_str(&rest[..index + 4]);
+        rest = &rest[index + 4..];
+
+        let trimmed = rest.trim_start();
+        let (value, after) = match trimmed.chars().next() {
+            Some(quote @ ('\'' | '"')) => match trimmed[1..].find(quote) {
+                Some(end) => (&trimmed[1..end + 1], &trimmed[end + 2..]),
+                None => break,
+            },
+            _ => match trimmed.find(')') {
+                Some(end) => (trimmed[..end].trim_end(), &trimmed[end..]),
+                None => break,
+            },
+        };
+
+        let replacement = if value.starts_with("data:") {
+            None
+        } else {
+            base.join(value).ok().and_then(&mut f)
+        };
+        match replacement {
+            Some(replacement) => out.push_str(&format!("\"{replacement}\"")),
+            None => out.push_str(&rest[..rest.len() - after.len()]),
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+
+    out
+}
+
+fn inlined_stylesheet_base() -> Url {
+    Url::parse("https://cohost.org/static/").unwrap()
+}
+
+/// Returns all URLs referenced by stylesheets that are inlined into standalone pages (mostly fonts).
+pub fn urls_in_inlined_stylesheets() -> Vec<Url> {
+    let mut urls = Vec::new();
+    for name in INLINED_STYLESHEETS {
+        if let Some(css) = inlined_stylesheet(name) {
+            map_css_urls(&css, &inlined_stylesheet_base(), |url| {
+                urls.push(url);
+                None
+            });
+        }
+    }
+    urls
+}
+
+/// Returns the original URLs of all cohost static files (`/static/...`) used in the page.
+pub fn cohost_static_urls_in_page(html: &str) -> Vec<Url> {
+    let mut urls = Vec::new();
+    for (index, _) in html.match_indices("/static/") {
+        let rest = &html[index + 8..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-' && c != '_')
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        if name.is_empty() || CDL_STATIC.iter().any(|(file, _)| *file == name) {
+            continue;
+        }
+        if let Ok(url) = inlined_stylesheet_base().join(name) {
+            urls.push(url);
+        }
+    }
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// Turns a rendered page into one that does not depend on the local server.
+///
+/// Stylesheets are inlined, scripts are removed, resource URLs are replaced with values from
+/// `resources` (keyed by original URL), and other local links point to cohost.org.
+/// Static files are embedded; cohost's static files (keyed by their cohost.org URL) are removed
+/// if they aren't in `resources`.
+pub fn make_standalone_page(html: &str, resources: &HashMap<Url, String>) -> String {
+    let map_url = |value: &str| -> Option<String> {
+        if let Some(url) = resolve_resource_url(value) {
+            return Some(
+                resources
+                    .get(&url)
+                    .cloned()
+                    .unwrap_or_else(|| url.to_string()),
+            );
+        }
+        if let Some(name) = value.strip_prefix("/static/") {
+            if let Some((file, contents)) = CDL_STATIC.iter().find(|(file, _)| *file == name) {
+                let content_type = content_type_for_ext(file.rsplit('.').next());
+                return Some(format!(
+                    "data:{content_type};base64,{}",
+                    BASE64_STANDARD.encode(contents)
+                ));
+            }
+            let url = inlined_stylesheet_base().join(name).ok()?;
+            return resources.get(&url).cloned();
+        }
+        if value.starts_with('/') && !value.starts_with("//") {
+            return Some(format!("https://cohost.org{value}"));
+        }
+        Some(value.to_string())
+    };
+
+    let doc = kuchikiki::parse_html().one(html);
+
+    let links: Vec<_> = doc.select("link").into_iter().flatten().collect();
+    for link in links {
+        let css = {
+            let attrs = link.attributes.borrow();
+            if attrs.get("rel") == Some("stylesheet") {
+                attrs
+                    .get("href")
+                    .and_then(|href| href.strip_prefix("/static/"))
+                    .and_then(inlined_stylesheet)
+            } else {
+                None
+            }
+        };
+
+        if let Some(css) = css {
+            let css = map_css_urls(&css, &inlined_stylesheet_base(), |url| {
+                Some(
+                    resources
+                        .get(&url)
+                        .cloned()
+                        .unwrap_or_else(|| url.to_string()),
+                )
+            });
+            let style = NodeRef::new_element(
+                QualName::new(None, ns!(html), local_name!("style")),
+                Vec::new(),
+            );
+            style.append(NodeRef::new_text(css));
+            link.as_node().insert_before(style);
+        }
+        link.as_node().detach();
+    }
+
+    let scripts: Vec<_> = doc.select("script").into_iter().flatten().collect();
+    for script in scripts {
+        script.as_node().detach();
+    }
+
+    for element in doc.select("*").into_iter().flatten() {
+        let mut attrs = element.attributes.borrow_mut();
+        // only used by client.js
+        attrs.remove("data-view-model");
+
+        for name in ["src", "href", "poster"] {
+            let Some(value) = attrs.get_mut(name) else {
+                continue;
+            };
+            match map_url(value) {
+                Some(url) => *value = url,
+                None => {
+                    attrs.remove(name);
+                }
+            }
+        }
+        if let Some(srcset) = attrs.get_mut("srcset") {
+            *srcset = map_srcset(srcset, map_url);
+            if srcset.is_empty() {
+                attrs.remove("srcset");
+            }
+        }
+    }
+
+    doc.to_string()
+}
+
+/// Placeholder that [make_offline_page] puts in place of blocked resources.
+pub const BLOCKED_RESOURCE_URL: &str = "/static/blocked.svg";
+
+/// Returns true if loading this URL would make a request to somewhere other than the local server.
+fn is_external_url(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with("//")
+        || Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Makes a rendered page load nothing from outside the local server.
+///
+/// Archived resources already point at the local server, so every remaining external resource URL
+/// is replaced with a placeholder, and a notice with the number of blocked resources is added.
+/// Links are left alone.
+pub fn make_offline_page(html: &str) -> String {
+    let local = Url::parse("http://localhost/").unwrap();
+    let mut blocked = HashSet::new();
+
+    let doc = kuchikiki::parse_html().one(html);
+
+    for element in doc.select("*").into_iter().flatten() {
+        let mut attrs = element.attributes.borrow_mut();
+
+        let mut names = vec!["src", "poster", "data"];
+        if &*element.name.local == "link" {
+            names.push("href");
+        }
+        for name in names {
+            if let Some(value) = attrs.get_mut(name) {
+                if is_external_url(value) {
+                    blocked.insert(value.trim().to_string());
+                    *value = BLOCKED_RESOURCE_URL.into();
+                }
+            }
+        }
+        if let Some(srcset) = attrs.get_mut("srcset") {
+            *srcset = map_srcset(srcset, |url| {
+                if is_external_url(url) {
+                    blocked.insert(url.to_string());
+                    Some(BLOCKED_RESOURCE_URL.into())
+                } else {
+                    Some(url.to_string())
+                }
+            });
+        }
+        if let Some(style) = attrs.get_mut("style") {
+            if style.contains("url(") {
+                *style = map_css_urls(style, &local, |url| {
+                    if url.origin() == local.origin() {
+                        return None;
+                    }
+                    blocked.insert(url.to_string());
+                    Some(BLOCKED_RESOURCE_URL.into())
+                });
+            }
+        }
+    }
+
+    if !blocked.is_empty() {
+        if let Ok(container) = doc.select_first("#page-container") {
+            let notice = NodeRef::new_element(
+                QualName::new(None, ns!(html), local_name!("div")),
+                vec![(
+                    ExpandedName::new(ns!(), local_name!("class")),
+                    Attribute {
+                        prefix: None,
+                        value: "offline-notice".into(),
+                    },
+                )],
+            );
+            let count = blocked.len();
+            notice.append(NodeRef::new_text(format!(
+                "offline mode: blocked {count} external {} that {} not archived",
+                if count == 1 { "resource" } else { "resources" },
+                if count == 1 { "was" } else { "were" },
+            )));
+            container.as_node().insert_before(notice);
+        }
+    }
+
+    doc.to_string()
+}
@@ -0,0 +1,179 @@
+use crate::data::{Database, PostShare};
+use crate::post::PostFromCohost;
+use crate::render::api_data::{cohost_api_post, GetDataError};
+use crate::render::feed::RenderFeedError;
+use crate::render::single_post::parse_post_id;
+use crate::render::PageRenderer;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tera::Context;
+
+#[derive(Debug, Serialize)]
+struct ShareTreeNode {
+    post_id: u64,
+    handle: String,
+    filename: String,
+    published_at: Option<String>,
+    is_transparent_share: bool,
+    children: Vec<ShareTreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+struct ShareListItem {
+    #[serde(flatten)]
+    share: PostShare,
+    /// Set if this is a share of a share, rather than of the post itself
+    shared_from: Option<String>,
+}
+
+/// Loads all archived shares that descend from a post, keyed by the post they directly share.
+async fn load_descendant_shares(
+    db: &Database,
+    post_id: u64,
+) -> Result<HashMap<u64, Vec<PostShare>>, GetDataError> {
+    let mut by_parent = HashMap::<u64, Vec<PostShare>>::new();
+    let mut seen = HashSet::from([post_id]);
+    let mut frontier = vec![post_id];
+
+    while !frontier.is_empty() {
+        let shares = db.shares_of_posts(&frontier).await?;
+        frontier.clear();
+
+        for share in shares {
+            if !seen.insert(share.post_id) {
+                continue;
+            }
+            frontier.push(share.post_id);
+            by_parent
+                .entry(share.share_of_post_id)
+                .or_default()
+                .push(share);
+        }
+    }
+
+    Ok(by_parent)
+}
+
+fn build_share_tree(
+    node: ShareTreeNode,
+    by_parent: &mut HashMap<u64, Vec<PostShare>>,
+) -> ShareTreeNode {
+    let children = by_parent.remove(&node.post_id).unwrap_or_default();
+
+    let children = children
+        .into_iter()
+        .map(|share| {
+            let child = ShareTreeNode {
+                post_id: share.post_id,
+                handle: share.handle,
+                filename: share.filename,
+                published_at: share.published_at,
+                is_transparent_share: share.is_transparent_share,
+                children: Vec::new(),
+            };
+            build_share_tree(child, by_parent)
+        })
+        .collect();
+
+    ShareTreeNode { children, ..node }
+}
+
+/// Loads a post and checks that it was posted by the project.
+async fn load_post(
+    db: &Database,
+    project: &str,
+    post: &str,
+) -> Result<PostFromCohost, GetDataError> {
+    let post_id = parse_post_id(post).map_err(|_| GetDataError::NotFound)?;
+    let post = cohost_api_post(db, 0, post_id).await?;
+
+    if post.posting_project.handle != project {
+        return Err(GetDataError::NotFound);
+    }
+    Ok(post)
+}
+
+impl PageRenderer {
+    /// Renders a list of every archived share of a post, including shares of shares.
+    pub async fn render_post_shares(
+        &self,
+        db: &Database,
+        project: &str,
+        post: &str,
+    ) -> Result<String, RenderFeedError> {
+        let post = load_post(db, project, post).await?;
+        let by_parent = load_descendant_shares(db, post.post_id).await?;
+
+        let handles: HashMap<_, _> = std::iter::once((post.post_id, project))
+            .chain(
+                by_parent
+                    .values()
+                    .flatten()
+                    .map(|share| (share.post_id, share.handle.as_str())),
+            )
+            .map(|(post, handle)| (post, handle.to_string()))
+            .collect();
+
+        let mut shares: Vec<_> = by_parent
+            .into_values()
+            .flatten()
+            .map(|share| ShareListItem {
+                shared_from: if share.share_of_post_id == post.post_id {
+                    None
+                } else {
+                    handles.get(&share.share_of_post_id).cloned()
+                },
+                share,
+            })
+            .collect();
+        shares.sort_by_key(|item| (item.share.published_at.clone(), item.share.post_id));
+
+        let transparent_count = shares
+            .iter()
+            .filter(|item| item.share.is_transparent_share)
+            .count();
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("post", &post);
+        template_ctx.insert("shares", &shares);
+        template_ctx.insert("transparent_count", &transparent_count);
+        template_ctx.insert("reply_count", &(shares.len() - transparent_count));
+
+        let body = self.tera.render("post_shares.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    /// Renders every archived share branching from the root of the post's share tree.
+    pub async fn render_share_tree(
+        &self,
+        db: &Database,
+        project: &str,
+        post: &str,
+    ) -> Result<String, RenderFeedError> {
+        let post = load_post(db, project, post).await?;
+        let root = post.share_tree.first().unwrap_or(&post);
+
+        let mut by_parent = load_descendant_shares(db, root.post_id).await?;
+        let share_count: usize = by_parent.values().map(|shares| shares.len()).sum();
+
+        let root = ShareTreeNode {
+            post_id: root.post_id,
+            handle: root.posting_project.handle.clone(),
+            filename: root.filename.clone(),
+            published_at: root.published_at.clone(),
+            is_transparent_share: false,
+            children: Vec::new(),
+        };
+        let tree = build_share_tree(root, &mut by_parent);
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("post", &post);
+        template_ctx.insert("tree", &tree);
+        template_ctx.insert("share_count", &share_count);
+
+        let body = self.tera.render("share_tree.html", &template_ctx)?;
+
+        Ok(body)
+    }
+}
@@ -6,12 +6,18 @@ use crate::render::md_render::{
     PostRenderRequest,
 };
 use crate::render::{rewrite, PageRenderer};
+use crate::server::content_type_for_ext;
 use axum::http::StatusCode;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use chrono::Utc;
+use deno_core::url::Url;
 use std::collections::HashMap;
 use std::convert::identity;
+use std::path::Path;
 use tera::Context;
 use thiserror::Error;
+use tokio::fs;
 
 #[derive(Debug, Error)]
 pub enum RenderSinglePostError {
@@ -52,11 +58,7 @@ impl PageRenderer {
         project: &str,
         post: &str,
     ) -> Result<String, RenderSinglePostError> {
-        let post_id = post
-            .split('-')
-            .next()
-            .and_then(|id| id.parse().ok())
-            .ok_or(RenderSinglePostError::InvalidPostId)?;
+        let post_id = parse_post_id(post)?;
 
         let mut post = match cohost_api_post(db, 0, post_id).await {
             Ok(post) => post,
@@ -83,41 +85,7 @@ impl PageRenderer {
                 .map_err(|e| RenderSinglePostError::Unknown(e))?;
         }
 
-        let mut rendered_comments = HashMap::new();
-
-        #[async_recursion::async_recursion]
-        async fn render_comment(
-            db: &Database,
-            md: &MarkdownRenderer,
-            comment: &CommentFromCohost,
-            comments: &mut HashMap<String, MarkdownRenderResult>,
-        ) -> Result<(), RenderSinglePostError> {
-            let resources = db
-                .get_saved_resource_urls_for_comment(&comment.comment.comment_id)
-                .await
-                .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
-
-            let result = md
-                .render_markdown(MarkdownRenderRequest {
-                    markdown: comment.comment.body.clone(),
-                    context: MarkdownRenderContext::Comment,
-                    published_at: comment.comment.posted_at_iso.clone(),
-                    has_cohost_plus: comment.comment.has_cohost_plus,
-                    resources,
-                })
-                .await
-                .map_err(|e| RenderSinglePostError::RenderComment(e))?;
-
-            comments.insert(comment.comment.comment_id.clone(), result);
-
-            for child in &comment.comment.children {
-                render_comment(db, md, child, comments).await?;
-            }
-            Ok(())
-        }
-        for comment in comments.values().flat_map(identity) {
-            render_comment(db, &self.md, comment, &mut rendered_comments).await?;
-        }
+        let rendered_comments = self.render_comments(db, &comments).await?;
 
         let mut rendered_posts = HashMap::new();
 
@@ -126,6 +94,9 @@ impl PageRenderer {
                 .get_saved_resource_urls_for_post(post.post_id)
                 .await
                 .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+            let links = rewrite::archived_cohost_links(db, &post.blocks)
+                .await
+                .map_err(RenderSinglePostError::Unknown)?;
 
             let result = self
                 .md
@@ -138,6 +109,8 @@ impl PageRenderer {
                         .unwrap_or_else(|| Utc::now().to_rfc3339()),
                     has_cohost_plus: post.has_cohost_plus,
                     resources,
+                    links,
+                    wayback_links: self.options.wayback_links,
                 })
                 .await
                 .map_err(|e| RenderSinglePostError::Render(post.post_id, e))?;
@@ -145,6 +118,20 @@ impl PageRenderer {
             rendered_posts.insert(post.post_id, result);
         }
 
+        let share_counts = db
+            .share_counts(&[post.post_id])
+            .await
+            .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+
+        let linked_from = db
+            .posts_linking_to(post.post_id)
+            .await
+            .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+        let linked_from = db
+            .backlinks(&linked_from)
+            .await
+            .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+
         let resources = db
             .get_saved_resource_urls_for_project(post.posting_project.project_id)
             .await
@@ -167,6 +154,8 @@ impl PageRenderer {
         template_ctx.insert("comments", &comments);
         template_ctx.insert("rendered_comments", &rendered_comments);
         template_ctx.insert("rendered_posts", &rendered_posts);
+        template_ctx.insert("share_counts", &share_counts);
+        template_ctx.insert("linked_from", &linked_from);
         template_ctx.insert(
             "rendered_project_description",
             &rendered_project_description,
@@ -179,4 +168,153 @@ impl PageRenderer {
 
         Ok(body)
     }
+
+    /// Renders a single post page as one HTML file that works without the server.
+    ///
+    /// All archived resources of the post, its share tree, and its comments are embedded as data
+    /// URIs, as are the static files used by the page.
+    pub async fn render_single_post_file(
+        &self,
+        db: &Database,
+        root_dir: &Path,
+        project: &str,
+        post: &str,
+    ) -> Result<String, RenderSinglePostError> {
+        let body = self.render_single_post(db, project, post).await?;
+
+        let post = cohost_api_post(db, 0, parse_post_id(post)?)
+            .await
+            .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+
+        let mut urls = Vec::new();
+        for post in std::iter::once(&post).chain(post.share_tree.iter()) {
+            urls.extend(
+                db.get_single_post_resources(post.post_id)
+                    .await
+                    .map_err(unknown)?,
+            );
+            urls.extend(
+                db.get_single_project_resources(post.posting_project.project_id)
+                    .await
+                    .map_err(unknown)?,
+            );
+
+            for comment in db.get_comments(post.post_id).await.map_err(unknown)? {
+                urls.extend(
+                    db.get_single_comment_resources(&comment.id)
+                        .await
+                        .map_err(unknown)?,
+                );
+                if let Some(project) = comment.posting_project_id {
+                    urls.extend(
+                        db.get_single_project_resources(project as u64)
+                            .await
+                            .map_err(unknown)?,
+                    );
+                }
+            }
+        }
+
+        let urls = urls
+            .iter()
+            .filter_map(|url| Url::parse(url).ok())
+            // every font also has a .woff2 version, which every browser that matters supports
+            .chain(
+                rewrite::urls_in_inlined_stylesheets()
+                    .into_iter()
+                    .filter(|url| !url.path().ends_with(".woff")),
+            )
+            .chain(rewrite::cohost_static_urls_in_page(&body));
+
+        let mut resources = HashMap::new();
+        for url in urls {
+            if resources.contains_key(&url) {
+                continue;
+            }
+            let Some(path) = db.get_url_file(&url).await.map_err(unknown)? else {
+                continue;
+            };
+
+            let data = match fs::read(root_dir.join(&path)).await {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("could not read {}: {e}", path.display());
+                    continue;
+                }
+            };
+
+            let content_type = match db.get_res_content_type(&url).await.map_err(unknown)? {
+                Some(content_type) => content_type,
+                None => {
+                    let ext = path.extension().and_then(|ext| ext.to_str());
+                    content_type_for_ext(ext).to_string()
+                }
+            };
+
+            let data_url = format!(
+                "data:{content_type};base64,{}",
+                BASE64_STANDARD.encode(data)
+            );
+            resources.insert(url, data_url);
+        }
+
+        Ok(rewrite::make_standalone_page(&body, &resources))
+    }
+
+    /// Renders all comments (including replies) in a post's comments map, keyed by comment ID.
+    pub async fn render_comments(
+        &self,
+        db: &Database,
+        comments: &HashMap<u64, Vec<CommentFromCohost>>,
+    ) -> Result<HashMap<String, MarkdownRenderResult>, RenderSinglePostError> {
+        let mut rendered_comments = HashMap::new();
+
+        for comment in comments.values().flat_map(identity) {
+            render_comment(db, &self.md, comment, &mut rendered_comments).await?;
+        }
+
+        Ok(rendered_comments)
+    }
+}
+
+pub(super) fn parse_post_id(post: &str) -> Result<u64, RenderSinglePostError> {
+    post.split('-')
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or(RenderSinglePostError::InvalidPostId)
+}
+
+fn unknown(e: impl Into<anyhow::Error>) -> RenderSinglePostError {
+    RenderSinglePostError::Unknown(e.into())
+}
+
+#[async_recursion::async_recursion]
+async fn render_comment(
+    db: &Database,
+    md: &MarkdownRenderer,
+    comment: &CommentFromCohost,
+    comments: &mut HashMap<String, MarkdownRenderResult>,
+) -> Result<(), RenderSinglePostError> {
+    let resources = db
+        .get_saved_resource_urls_for_comment(&comment.comment.comment_id)
+        .await
+        .map_err(|e| RenderSinglePostError::Unknown(e.into()))?;
+
+    let result = md
+        .render_markdown(MarkdownRenderRequest {
+            markdown: comment.comment.body.clone(),
+            context: MarkdownRenderContext::Comment,
+            published_at: comment.comment.posted_at_iso.clone(),
+            has_cohost_plus: comment.comment.has_cohost_plus,
+            resources,
+        })
+        .await
+        .map_err(|e| RenderSinglePostError::RenderComment(e))?;
+
+    comments.insert(comment.comment.comment_id.clone(), result);
+
+    for child in &comment.comment.children {
+        render_comment(db, md, child, comments).await?;
+    }
+    Ok(())
 }
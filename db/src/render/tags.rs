@@ -0,0 +1,181 @@
+use crate::data::Database;
+use crate::render::api_data::GetDataError;
+use crate::render::feed::RenderFeedError;
+use crate::render::PageRenderer;
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const TAGS_PER_PAGE: u64 = 200;
+const TAG_CLOUD_SIZE: u64 = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagIndexQuery {
+    #[serde(default)]
+    page: u64,
+    /// Only show tags containing this
+    #[serde(default)]
+    q: String,
+}
+
+impl TagIndexQuery {
+    fn fmt_query(&self) -> String {
+        let mut out = Vec::new();
+
+        if self.page > 0 {
+            out.push(format!("page={}", self.page));
+        }
+        if !self.q.is_empty() {
+            out.push(format!("q={}", urlencoding::encode(&self.q)));
+        }
+
+        let mut out = out.join("&");
+        if !out.is_empty() {
+            out.insert(0, '?');
+        }
+        out
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TagCount {
+    tag: String,
+    count: u64,
+    /// 1 to 5, for tag clouds
+    size: u32,
+}
+
+/// Sizes are logarithmic, because a few tags are used much more often than all the others.
+fn tag_counts_with_size(counts: Vec<(String, u64)>) -> Vec<TagCount> {
+    let max = counts.iter().map(|(_, count)| *count).max().unwrap_or(1);
+    let max = (max as f64).ln_1p();
+
+    counts
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            size: 1 + ((count as f64).ln_1p() / max * 4.).round() as u32,
+            tag,
+            count,
+        })
+        .collect()
+}
+
+impl PageRenderer {
+    pub async fn render_tag_index(
+        &self,
+        db: &Database,
+        query: TagIndexQuery,
+    ) -> Result<String, RenderFeedError> {
+        let search = query.q.trim();
+        let counts = db
+            .tag_counts(None, search, query.page * TAGS_PER_PAGE, TAGS_PER_PAGE)
+            .await
+            .map_err(GetDataError::from)?;
+        let total = db
+            .distinct_tag_count(None, search)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let max_page = total.saturating_sub(1) / TAGS_PER_PAGE;
+        let on_prev_page = if query.page > 0 {
+            TagIndexQuery {
+                page: query.page - 1,
+                ..query.clone()
+            }
+            .fmt_query()
+        } else {
+            "".into()
+        };
+        let on_next_page = if query.page < max_page {
+            TagIndexQuery {
+                page: query.page + 1,
+                ..query.clone()
+            }
+            .fmt_query()
+        } else {
+            "".into()
+        };
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("query", &query);
+        template_ctx.insert("total", &total);
+        template_ctx.insert("tags", &tag_counts_with_size(counts));
+        template_ctx.insert("prev_page", &on_prev_page);
+        template_ctx.insert("next_page", &on_next_page);
+
+        let body = self.tera.render("tag_index.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    pub async fn render_project_tags(
+        &self,
+        db: &Database,
+        project: &str,
+    ) -> Result<String, RenderFeedError> {
+        let project_id = db
+            .project_id_for_handle(project)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let mut counts = db
+            .tag_counts(Some(project_id), "", 0, TAG_CLOUD_SIZE)
+            .await
+            .map_err(GetDataError::from)?;
+        let total = db
+            .distinct_tag_count(Some(project_id), "")
+            .await
+            .map_err(GetDataError::from)?;
+
+        counts.sort_by_key(|(tag, _)| tag.to_lowercase());
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("project", project);
+        template_ctx.insert("total", &total);
+        template_ctx.insert("tags", &tag_counts_with_size(counts));
+
+        let body = self.tera.render("tag_cloud.html", &template_ctx)?;
+
+        Ok(body)
+    }
+
+    pub async fn render_tag_detail(
+        &self,
+        db: &Database,
+        tag: &str,
+    ) -> Result<String, RenderFeedError> {
+        let canon_tag = db
+            .canonical_tag_capitalization(tag)
+            .await
+            .map_err(GetDataError::from)?
+            .unwrap_or(tag.to_string());
+
+        let synonyms = db
+            .synonym_tags(&canon_tag)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let related_tags = db
+            .related_tags(&canon_tag, &synonyms)
+            .await
+            .map_err(GetDataError::from)?;
+
+        let all_tags: Vec<_> = std::iter::once(canon_tag.clone())
+            .chain(synonyms.iter().cloned())
+            .collect();
+        let usage = db.tag_usage(&all_tags).await.map_err(GetDataError::from)?;
+
+        if usage.post_count == 0 && synonyms.is_empty() && related_tags.is_empty() {
+            return Err(GetDataError::NotFound.into());
+        }
+
+        let mut template_ctx = Context::new();
+        template_ctx.insert("tag", &canon_tag);
+        template_ctx.insert("synonym_tags", &synonyms);
+        template_ctx.insert("related_tags", &related_tags);
+        template_ctx.insert("usage", &usage);
+
+        let body = self.tera.render("tag_detail.html", &template_ctx)?;
+
+        Ok(body)
+    }
+}
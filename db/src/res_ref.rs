@@ -105,7 +105,15 @@ impl ResourceRefs for String {
     }
 }
 
-fn urls_in_srcset(s: &str) -> Vec<String> {
+pub(crate) fn urls_in_srcset(s: &str) -> Vec<String> {
+    srcset_candidates(s)
+        .into_iter()
+        .map(|(url, _)| url)
+        .collect()
+}
+
+/// Returns the URL and descriptor (e.g. `2x`, or empty) of every image candidate in a srcset.
+pub(crate) fn srcset_candidates(s: &str) -> Vec<(String, String)> {
     // https://html.spec.whatwg.org/multipage/images.html#srcset-attributes
     struct Reader<'a> {
         chars: std::str::Chars<'a>,
@@ -125,7 +133,7 @@ fn urls_in_srcset(s: &str) -> Vec<String> {
     };
     reader.next();
 
-    let mut urls = Vec::new();
+    let mut candidates = Vec::new();
 
     while reader.peek().is_some() {
         // 1. whitespace*
@@ -136,6 +144,7 @@ fn urls_in_srcset(s: &str) -> Vec<String> {
         let mut url = String::new();
         if reader.peek() == Some(',') {
             // invalid
+            reader.next();
             continue;
         }
         while reader.peek().map_or(false, |c| !c.is_ascii_whitespace()) {
@@ -146,24 +155,26 @@ fn urls_in_srcset(s: &str) -> Vec<String> {
         }
         if url.ends_with(',') {
             url.pop();
-            urls.push(url);
+            candidates.push((url, String::new()));
             continue;
         }
-        if !url.is_empty() {
-            urls.push(url);
-        }
 
         // 3. whitespace*
         while reader.peek().map_or(false, |c| c.is_ascii_whitespace()) {
             reader.next();
         }
         // 4. descriptor?
-        while reader
+        let mut descriptor = String::new();
+        while let Some(c) = reader
             .peek()
-            .map_or(false, |c| !c.is_ascii_whitespace() && c != ',')
+            .filter(|c| !c.is_ascii_whitespace() && *c != ',')
         {
+            descriptor.push(c);
             reader.next();
         }
+        if !url.is_empty() {
+            candidates.push((url, descriptor));
+        }
         // 5. whitespace*
         while reader.peek().map_or(false, |c| c.is_ascii_whitespace()) {
             reader.next();
@@ -172,7 +183,7 @@ fn urls_in_srcset(s: &str) -> Vec<String> {
         reader.next();
     }
 
-    urls
+    candidates
 }
 
 #[test]
@@ -0,0 +1,324 @@
+use crate::Config;
+use anyhow::{bail, Context};
+use regex::Regex;
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceRuleAction {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// Either a number of bytes or a string like `"50 MB"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FileSizeConfig {
+    Bytes(u64),
+    Text(String),
+}
+
+/// A resource rule as written in the config file.
+#[derive(Debug, Deserialize)]
+pub struct ResourceRuleConfig {
+    #[serde(default)]
+    pub action: ResourceRuleAction,
+    /// Host name, where `*` matches anything (e.g. `*.example.com`)
+    pub domain: Option<String>,
+    /// Glob for the URL path, where `*` matches within a path segment and `**` matches anything
+    pub path: Option<String>,
+    /// Regex for the entire URL
+    pub url_regex: Option<String>,
+    #[serde(default)]
+    pub content_types: Vec<String>,
+    #[serde(default)]
+    pub deny_content_types: Vec<String>,
+    pub max_size: Option<FileSizeConfig>,
+}
+
+#[derive(Debug)]
+pub struct ResourceRule {
+    action: ResourceRuleAction,
+    domain: Option<Regex>,
+    path: Option<Regex>,
+    url_regex: Option<Regex>,
+    content_types: Vec<String>,
+    deny_content_types: Vec<String>,
+    pub max_size: Option<u64>,
+}
+
+/// Ordered list of rules for fetching resources. The first rule matching a URL applies.
+/// URLs that don't match any rule are fetched without restrictions.
+#[derive(Debug, Default)]
+pub struct ResourceRules {
+    rules: Vec<ResourceRule>,
+}
+
+fn parse_file_size(size: &FileSizeConfig) -> anyhow::Result<u64> {
+    let text = match size {
+        FileSizeConfig::Bytes(bytes) => return Ok(*bytes),
+        FileSizeConfig::Text(text) => text.trim(),
+    };
+
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid file size {text:?}"))?;
+
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        unit => bail!("unknown unit {unit:?} in file size {text:?}"),
+    };
+
+    Ok((number * factor as f64) as u64)
+}
+
+/// Converts a wildcard pattern to an anchored regex.
+/// `*` matches anything except `separator`, and `**` matches anything.
+fn wildcard_regex(pattern: &str, separator: Option<char>) -> anyhow::Result<Regex> {
+    let mut out = String::from("(?i)^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push_str(".*");
+            }
+            '*' => match separator {
+                Some(sep) => out.push_str(&format!("[^{}]*", regex::escape(&sep.to_string()))),
+                None => out.push_str(".*"),
+            },
+            '?' => match separator {
+                Some(sep) => out.push_str(&format!("[^{}]", regex::escape(&sep.to_string()))),
+                None => out.push('.'),
+            },
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push('$');
+
+    Regex::new(&out).with_context(|| format!("invalid pattern {pattern:?}"))
+}
+
+/// Returns the MIME type without parameters.
+fn content_type_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn content_type_matches(pattern: &str, essence: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => essence
+            .split_once('/')
+            .map_or(false, |(ty, _)| ty == prefix),
+        None => pattern == "*" || pattern == essence,
+    }
+}
+
+#[test]
+fn test_resource_rule_patterns() {
+    let size = |text: &str| parse_file_size(&FileSizeConfig::Text(text.into())).ok();
+    assert_eq!(parse_file_size(&FileSizeConfig::Bytes(123)).ok(), Some(123));
+    assert_eq!(size("123"), Some(123));
+    assert_eq!(size("50 MB"), Some(50 * 1024 * 1024));
+    assert_eq!(size(" 2kib "), Some(2048));
+    assert_eq!(size("1.5g"), Some(1536 * 1024 * 1024));
+    assert_eq!(size("50 parsecs"), None);
+    assert_eq!(size("MB"), None);
+    assert_eq!(size("1.2.3 MB"), None);
+
+    let domain = wildcard_regex("*.example.com", None).unwrap();
+    assert!(domain.is_match("media.example.com"));
+    assert!(domain.is_match("a.b.EXAMPLE.com"));
+    assert!(!domain.is_match("example.com"));
+    assert!(!domain.is_match("media.example.com.evil.net"));
+    assert!(!domain.is_match("mediaexample.com"));
+
+    let one_segment = wildcard_regex("/a/*/b", Some('/')).unwrap();
+    assert!(one_segment.is_match("/a/x/b"));
+    assert!(!one_segment.is_match("/a/x/y/b"));
+    assert!(!one_segment.is_match("/a/x/b/c"));
+
+    let any_depth = wildcard_regex("/a/**", Some('/')).unwrap();
+    assert!(any_depth.is_match("/a/x"));
+    assert!(any_depth.is_match("/a/x/y/b"));
+    assert!(!any_depth.is_match("/b/a/x"));
+
+    assert!(content_type_matches("image/*", "image/png"));
+    assert!(content_type_matches("image/*", "image/svg+xml"));
+    assert!(!content_type_matches("image/*", "video/mp4"));
+    assert!(!content_type_matches("image/*", "imagery/png"));
+    assert!(!content_type_matches("image/*", "image"));
+    assert!(content_type_matches("*", "video/mp4"));
+    assert!(content_type_matches("video/mp4", "video/mp4"));
+    assert_eq!(
+        content_type_essence(" Image/PNG; charset=binary"),
+        "image/png"
+    );
+}
+
+impl ResourceRule {
+    fn from_config(rule: &ResourceRuleConfig) -> anyhow::Result<Self> {
+        let domain = rule
+            .domain
+            .as_deref()
+            .map(|domain| wildcard_regex(domain, None))
+            .transpose()
+            .context("domain")?;
+        let path = rule
+            .path
+            .as_deref()
+            .map(|path| {
+                let path = if path.starts_with('/') {
+                    path.to_string()
+                } else {
+                    format!("/{path}")
+                };
+                wildcard_regex(&path, Some('/'))
+            })
+            .transpose()
+            .context("path")?;
+        let url_regex = rule
+            .url_regex
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .context("url_regex")?;
+        let max_size = rule
+            .max_size
+            .as_ref()
+            .map(parse_file_size)
+            .transpose()
+            .context("max_size")?;
+
+        let normalize = |items: &[String]| -> Vec<String> {
+            items
+                .iter()
+                .map(|item| item.trim().to_ascii_lowercase())
+                .collect()
+        };
+
+        Ok(Self {
+            action: rule.action,
+            domain,
+            path,
+            url_regex,
+            content_types: normalize(&rule.content_types),
+            deny_content_types: normalize(&rule.deny_content_types),
+            max_size,
+        })
+    }
+
+    fn deny_domain(domain: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            action: ResourceRuleAction::Deny,
+            domain: Some(wildcard_regex(domain, None)?),
+            path: None,
+            url_regex: None,
+            content_types: Vec::new(),
+            deny_content_types: Vec::new(),
+            max_size: None,
+        })
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        if let Some(domain) = &self.domain {
+            if !url.host_str().map_or(false, |host| domain.is_match(host)) {
+                return false;
+            }
+        }
+        if let Some(path) = &self.path {
+            if !path.is_match(url.path()) {
+                return false;
+            }
+        }
+        if let Some(url_regex) = &self.url_regex {
+            if !url_regex.is_match(url.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn is_denied(&self) -> bool {
+        self.action == ResourceRuleAction::Deny
+    }
+
+    /// Returns true if a resource with this content type may be stored.
+    /// If there's an allow list, resources without a content type are not stored.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        let essence = content_type_essence(content_type);
+
+        if self
+            .deny_content_types
+            .iter()
+            .any(|pattern| content_type_matches(pattern, &essence))
+        {
+            return false;
+        }
+
+        self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|pattern| content_type_matches(pattern, &essence))
+    }
+}
+
+impl ResourceRules {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+
+        // the old way of doing this
+        for domain in &config.do_not_fetch_domains {
+            rules.push(
+                ResourceRule::deny_domain(domain)
+                    .with_context(|| format!("do_not_fetch_domains: {domain}"))?,
+            );
+        }
+
+        for (i, rule) in config.resource_rules.iter().enumerate() {
+            if rule.domain.is_none() && rule.path.is_none() && rule.url_regex.is_none() {
+                bail!(
+                    "resource rule {} needs at least one of domain, path, or url_regex",
+                    i + 1
+                );
+            }
+
+            rules.push(
+                ResourceRule::from_config(rule)
+                    .with_context(|| format!("resource rule {}", i + 1))?,
+            );
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Returns a hash of the rules in the config, to notice when they change.
+    pub fn config_hash(config: &Config) -> String {
+        let rules = format!(
+            "{:?}\n{:?}",
+            config.do_not_fetch_domains, config.resource_rules
+        );
+        hex::encode(Sha256::digest(rules))
+    }
+
+    /// Returns the first rule matching the URL.
+    pub fn rule_for(&self, url: &Url) -> Option<&ResourceRule> {
+        self.rules.iter().find(|rule| rule.matches(url))
+    }
+}
@@ -1,5 +1,17 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    asks (id) {
+        id -> Text,
+        post_id -> Integer,
+        asking_project_id -> Nullable<Integer>,
+        asking_handle -> Nullable<Text>,
+        anon -> Bool,
+        logged_in -> Bool,
+        sent_at -> Text,
+    }
+}
+
 diesel::table! {
     comment_resources (comment_id, url) {
         comment_id -> Text,
@@ -47,6 +59,21 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    mentions (post_id, handle) {
+        post_id -> Integer,
+        handle -> Text,
+    }
+}
+
+diesel::table! {
+    post_links (post_id, linked_post_id) {
+        post_id -> Integer,
+        linked_post_id -> Integer,
+        linked_handle -> Text,
+    }
+}
+
 diesel::table! {
     post_related_projects (post_id, project_id) {
         post_id -> Integer,
@@ -119,6 +146,39 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    resource_failures (url) {
+        url -> Text,
+        error_class -> Text,
+        status_code -> Nullable<Integer>,
+        message -> Text,
+        attempts -> Integer,
+        first_failed_at -> Text,
+        last_failed_at -> Text,
+        skip -> Bool,
+    }
+}
+
+diesel::table! {
+    resource_validators (url) {
+        url -> Text,
+        etag -> Nullable<Text>,
+        last_modified -> Nullable<Text>,
+        size -> BigInt,
+        sha256 -> Text,
+        checked_at -> Text,
+    }
+}
+
+diesel::table! {
+    resource_versions (url, file_path) {
+        url -> Text,
+        file_path -> Text,
+        sha256 -> Nullable<Text>,
+        replaced_at -> Text,
+    }
+}
+
 diesel::table! {
     url_files (url) {
         url -> Text,
@@ -126,12 +186,15 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(asks -> posts (post_id));
 diesel::joinable!(comment_resources -> comments (comment_id));
 diesel::joinable!(comments -> posts (post_id));
 diesel::joinable!(comments -> projects (posting_project_id));
 diesel::joinable!(draft_nonces -> posts (post_id));
 diesel::joinable!(likes -> posts (to_post_id));
 diesel::joinable!(likes -> projects (from_project_id));
+diesel::joinable!(mentions -> posts (post_id));
+diesel::joinable!(post_links -> posts (post_id));
 diesel::joinable!(post_related_projects -> posts (post_id));
 diesel::joinable!(post_related_projects -> projects (project_id));
 diesel::joinable!(post_resources -> posts (post_id));
@@ -140,12 +203,15 @@ diesel::joinable!(posts -> projects (posting_project_id));
 diesel::joinable!(project_resources -> projects (project_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    asks,
     comment_resources,
     comments,
     data_migration_state,
     draft_nonces,
     follows,
     likes,
+    mentions,
+    post_links,
     post_related_projects,
     post_resources,
     post_tags,
@@ -154,5 +220,8 @@ diesel::allow_tables_to_appear_in_same_query!(
     projects,
     related_tags,
     resource_content_types,
+    resource_failures,
+    resource_validators,
+    resource_versions,
     url_files,
 );
@@ -1,13 +1,21 @@
 use crate::bundled_files::CDL_STATIC;
 use crate::data::Database;
-use crate::render::api_data::{cohost_api_post, GetDataError};
+use crate::render::api_data::{
+    cohost_api_post, cohost_api_project_comments, CommentInContext, GetDataError,
+};
+use crate::render::failures::FailuresQuery;
 use crate::render::feed::TagFeedQuery;
+use crate::render::project_comments::{ProjectCommentsQuery, COMMENTS_PER_PAGE};
 use crate::render::project_profile::ProjectProfileQuery;
-use crate::render::PageRenderer;
+use crate::render::projects::{FollowList, ProjectDirectoryQuery};
+use crate::render::rewrite::make_offline_page;
+use crate::render::tags::TagIndexQuery;
+use crate::render::{PageRenderer, RenderOptions};
 use crate::Config;
 use axum::body::Body;
-use axum::extract::{Path, Query, State};
+use axum::extract::{Path, Query, Request, State};
 use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{response, Router};
@@ -27,39 +35,169 @@ pub struct ServerState {
     db: Database,
     root_dir: PathBuf,
     page_renderer: PageRenderer,
+    allow_indexing: bool,
 }
 
 type SharedServerState = Arc<ServerState>;
 
 pub async fn serve(config: Config, db: SqliteConnection, on_listen: impl FnOnce()) {
-    let db = Database::new(db);
+    let mut db = Database::new(db);
+    if config.public_mirror {
+        if let Err(e) = db.hide_non_public_projects(&config.public_opt_out).await {
+            error!("could not load project privacy settings: {e}");
+            return;
+        }
+    }
 
-    let routes = Router::new()
+    let mut routes = Router::new()
         .route("/rc/tagged/:tag", get(get_global_tagged))
+        .route("/rc/projects", get(get_project_directory))
+        .route("/rc/tags", get(get_tag_index))
+        .route("/rc/tags/:tag", get(get_tag_detail))
+        .route("/rc/archive", get(get_global_archive))
+        .route("/rc/archive/:year/:month", get(get_global_archive_month))
+        .route("/rc/archive/:year/:month/:day", get(get_global_archive_day))
         .route("/:project/post/:post", get(get_single_post))
+        .route(
+            "/:project/post/:post/download",
+            get(get_single_post_download),
+        )
+        .route("/:project/post/:post/shares", get(get_post_shares))
+        .route("/:project/post/:post/tree", get(get_share_tree))
         .route("/:project", get(get_profile))
         .route("/:project/tagged/:tag", get(get_profile_tagged))
-        .route("/:project/liked-posts", get(get_liked))
-        .route("/:project/dashboard", get(get_dashboard))
+        .route("/:project/tags", get(get_project_tags))
+        .route("/:project/comments", get(get_project_comments))
+        .route("/:project/asks", get(get_asks))
+        .route("/:project/asks/sent", get(get_sent_asks))
+        .route("/:project/mentions", get(get_mentions))
+        .route("/:project/linked-from", get(get_linked_from))
+        .route("/:project/following", get(get_following))
+        .route("/:project/followers", get(get_followers))
+        .route("/:project/archive", get(get_archive))
+        .route("/:project/archive/:year/:month", get(get_archive_month))
+        .route("/:project/archive/:year/:month/:day", get(get_archive_day))
         .route("/api/post/:post", get(api_get_post))
+        .route(
+            "/api/project/:project/comments",
+            get(api_get_project_comments),
+        )
         .route("/r/:proto/:domain/*url", get(get_resource))
         .route("/r/:proto/:domain/", get(get_resource))
         .route("/r", get(get_resource_url))
         .route("/static/:file", get(get_static))
-        .route("/", get(get_index))
-        .with_state(Arc::new(ServerState {
-            db,
-            root_dir: PathBuf::from(config.root_dir),
-            page_renderer: PageRenderer::new(),
-        }));
-
-    let bind_addr = format!("127.0.0.1:{}", config.server_port);
-    let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
+        .route("/", get(get_index));
+
+    if config.public_mirror {
+        routes = routes.route("/robots.txt", get(get_robots_txt));
+    } else {
+        // these are about the person who made the archive
+        routes = routes
+            .route("/rc/failures", get(get_failures))
+            .route("/:project/liked-posts", get(get_liked))
+            .route("/:project/dashboard", get(get_dashboard));
+    }
+
+    let mut routes = routes.with_state(Arc::new(ServerState {
+        db,
+        root_dir: PathBuf::from(config.root_dir),
+        page_renderer: PageRenderer::with_options(RenderOptions {
+            wayback_links: config.wayback_links,
+            public_mirror: config.public_mirror,
+        }),
+        allow_indexing: config.public_allow_indexing,
+    }));
+
+    if config.offline_mode {
+        routes = routes.layer(middleware::from_fn(offline_mode));
+    }
+
+    if config.public_mirror && !config.public_allow_indexing {
+        routes = routes.layer(middleware::map_response(no_index));
+    }
+
+    let listener =
+        tokio::net::TcpListener::bind((config.server_address.as_str(), config.server_port))
+            .await
+            .unwrap();
+    let bind_addr = listener.local_addr().unwrap();
+    if !bind_addr.ip().is_loopback() && !config.public_mirror {
+        warn!(
+            "everything in the archive, including private projects, is reachable on {bind_addr}. \
+            set public_mirror to hide them"
+        );
+    }
     info!("serving: http://{bind_addr}");
     on_listen();
     axum::serve(listener, routes).await.unwrap();
 }
 
+async fn get_robots_txt(State(state): State<SharedServerState>) -> Response {
+    let body = if state.allow_indexing {
+        "User-agent: *\nDisallow: /api/\nDisallow: /*/download\n"
+    } else {
+        "User-agent: *\nDisallow: /\n"
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(Body::new(body.to_string()))
+        .unwrap()
+}
+
+/// Asks search engines not to index anything, in case they don't read robots.txt.
+async fn no_index(mut response: Response) -> Response {
+    response.headers_mut().insert(
+        "x-robots-tag",
+        HeaderValue::from_static("noindex, nofollow"),
+    );
+    response
+}
+
+/// Only allows loading things from the local server.
+const OFFLINE_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+    img-src 'self' data: blob:; media-src 'self' blob:; font-src 'self' data:; \
+    style-src 'self' 'unsafe-inline'; script-src 'self'; object-src 'none'; frame-src 'none'; \
+    base-uri 'self'; form-action 'self'";
+
+/// Replaces external resources in pages with placeholders and tells the browser not to load any.
+async fn offline_mode(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    parts.headers.insert(
+        "content-security-policy",
+        HeaderValue::from_static(OFFLINE_CONTENT_SECURITY_POLICY),
+    );
+
+    let is_page = parts
+        .headers
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    // downloaded posts are standalone pages that are meant to be opened elsewhere
+    let is_download = parts.headers.contains_key("content-disposition");
+    if !is_page || is_download {
+        return Response::from_parts(parts, body);
+    }
+
+    let html = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(html) => html,
+        Err(e) => {
+            error!("failed to read page: {e}");
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::new(String::new()))
+                .unwrap();
+        }
+    };
+    let html = make_offline_page(&String::from_utf8_lossy(&html));
+
+    parts.headers.remove("content-length");
+    Response::from_parts(parts, Body::new(html))
+}
+
 #[derive(Debug, Error)]
 enum ApiError {
     #[error(transparent)]
@@ -107,6 +245,52 @@ async fn api_get_post(
         .unwrap())
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiProjectComments {
+    total: u64,
+    page: u64,
+    comments: Vec<CommentInContext>,
+}
+
+async fn api_get_project_comments(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<ProjectCommentsQuery>,
+) -> response::Result<Response> {
+    let project_id = state
+        .db
+        .project_id_for_handle(&project)
+        .await
+        .map_err(|e| ApiError::Data(e.into()))?;
+    let total = state
+        .db
+        .project_comment_count(project_id)
+        .await
+        .map_err(|e| ApiError::Data(e.into()))?;
+    let comments = cohost_api_project_comments(
+        &state.db,
+        0,
+        project_id,
+        query.page * COMMENTS_PER_PAGE,
+        COMMENTS_PER_PAGE,
+    )
+    .await
+    .map_err(ApiError::Data)?;
+
+    let result = ApiProjectComments {
+        total,
+        page: query.page,
+        comments,
+    };
+    let body = serde_json::to_string(&result).map_err(|e| ApiError::Unknown(e.into()))?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
 async fn get_single_post(
     State(state): State<SharedServerState>,
     Path((project, post)): Path<(String, String)>,
@@ -124,6 +308,32 @@ async fn get_single_post(
         .unwrap())
 }
 
+async fn get_single_post_download(
+    State(state): State<SharedServerState>,
+    Path((project, post)): Path<(String, String)>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_single_post_file(&state.db, &state.root_dir, &project, &post)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    let file_name: String = format!("{project}-{post}.html")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .header(
+            "content-disposition",
+            format!("attachment; filename=\"{file_name}\""),
+        )
+        .body(Body::new(body))
+        .unwrap())
+}
+
 async fn get_global_tagged(
     State(state): State<SharedServerState>,
     uri: Uri,
@@ -143,6 +353,200 @@ async fn get_global_tagged(
         .unwrap())
 }
 
+async fn get_tag_index(
+    State(state): State<SharedServerState>,
+    Query(query): Query<TagIndexQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_tag_index(&state.db, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_project_directory(
+    State(state): State<SharedServerState>,
+    Query(query): Query<ProjectDirectoryQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_project_directory(&state.db, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_tag_detail(
+    State(state): State<SharedServerState>,
+    Path(tag): Path<String>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_tag_detail(&state.db, &tag)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_project_tags(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_project_tags(&state.db, &project)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_project_comments(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<ProjectCommentsQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_project_comments(&state.db, &project, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_asks(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_asks(&state.db, &project, false, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_sent_asks(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_asks(&state.db, &project, true, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_mentions(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_backlinks(&state.db, &project, false, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_linked_from(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_backlinks(&state.db, &project, true, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_following(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<ProjectDirectoryQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_project_follows(&state.db, &project, FollowList::Following, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_followers(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+    Query(query): Query<ProjectDirectoryQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_project_follows(&state.db, &project, FollowList::Followers, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
 async fn get_liked(
     State(state): State<SharedServerState>,
     Path(project): Path<String>,
@@ -179,6 +583,40 @@ async fn get_dashboard(
         .unwrap())
 }
 
+async fn get_post_shares(
+    State(state): State<SharedServerState>,
+    Path((project, post)): Path<(String, String)>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_post_shares(&state.db, &project, &post)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_share_tree(
+    State(state): State<SharedServerState>,
+    Path((project, post)): Path<(String, String)>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_share_tree(&state.db, &project, &post)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
 async fn get_profile(
     State(state): State<SharedServerState>,
     Path(project): Path<String>,
@@ -215,6 +653,107 @@ async fn get_profile_tagged(
         .unwrap())
 }
 
+async fn get_global_archive(State(state): State<SharedServerState>) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive(&state.db, None)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_global_archive_month(
+    State(state): State<SharedServerState>,
+    Path((year, month)): Path<(i32, u32)>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive_month(&state.db, None, year, month)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_global_archive_day(
+    State(state): State<SharedServerState>,
+    Path(date): Path<(i32, u32, u32)>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive_day(&state.db, None, date, query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_archive(
+    State(state): State<SharedServerState>,
+    Path(project): Path<String>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive(&state.db, Some(&project))
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_archive_month(
+    State(state): State<SharedServerState>,
+    Path((project, year, month)): Path<(String, i32, u32)>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive_month(&state.db, Some(&project), year, month)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
+async fn get_archive_day(
+    State(state): State<SharedServerState>,
+    Path((project, year, month, day)): Path<(String, i32, u32, u32)>,
+    Query(query): Query<TagFeedQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_archive_day(&state.db, Some(&project), (year, month, day), query)
+        .await
+        .map_err(|e| render_error_page(&state, e.status(), format!("{e}")))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
 async fn get_index(State(state): State<SharedServerState>) -> response::Result<Response> {
     let body = state
         .page_renderer
@@ -231,6 +770,25 @@ async fn get_index(State(state): State<SharedServerState>) -> response::Result<R
         .unwrap())
 }
 
+async fn get_failures(
+    State(state): State<SharedServerState>,
+    Query(query): Query<FailuresQuery>,
+) -> response::Result<Response> {
+    let body = state
+        .page_renderer
+        .render_failures_page(&state.db, &query)
+        .await
+        .map_err(|e| {
+            render_error_page(&state, StatusCode::INTERNAL_SERVER_ERROR, format!("{e}"))
+        })?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::new(body))
+        .unwrap())
+}
+
 fn render_error_page(state: &ServerState, status: StatusCode, message: String) -> Response {
     let body = state.page_renderer.render_error_page(&message);
 
@@ -374,6 +932,25 @@ async fn get_resource_url(
 }
 
 async fn get_resource_impl(state: &ServerState, url: Url, headers: HeaderMap) -> Response {
+    match state.db.is_resource_hidden(&url).await {
+        Ok(false) => (),
+        Ok(true) => {
+            return render_error_page(
+                state,
+                StatusCode::NOT_FOUND,
+                "no such downloaded file".into(),
+            );
+        }
+        Err(e) => {
+            error!("failed to look up file: {e}");
+            return render_error_page(
+                state,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to look up file".into(),
+            );
+        }
+    }
+
     let url_file = match state.db.get_url_file(&url).await {
         Ok(path) => path,
         Err(e) => {
@@ -432,7 +1009,7 @@ async fn get_resource_impl(state: &ServerState, url: Url, headers: HeaderMap) ->
     .await
 }
 
-fn content_type_for_ext(ext: Option<&str>) -> &'static str {
+pub(crate) fn content_type_for_ext(ext: Option<&str>) -> &'static str {
     match ext {
         Some("avif") => "image/avif",
         Some("css") => "text/css; charset=utf-8",
@@ -442,13 +1019,21 @@ fn content_type_for_ext(ext: Option<&str>) -> &'static str {
         Some("js") => "application/javascript; charset=utf-8",
         Some("jxl") => "image/jxl",
         Some("m4a") => "audio/mp4",
+        Some("mkv") => "video/x-matroska",
+        Some("mov") => "video/quicktime",
         Some("mp3") => "audio/mp3",
+        Some("mp4") => "video/mp4",
+        Some("otf") => "font/otf",
+        Some("pdf") => "application/pdf",
         Some("png") => "image/png",
         Some("svg") => "image/svg+xml",
+        Some("ttf") => "font/ttf",
         Some("wav") => "audio/wav",
+        Some("webm") => "video/webm",
         Some("webp") => "image/webp",
         Some("woff") => "font/woff",
         Some("woff2") => "font/woff2",
+        Some("zip") => "application/zip",
         _ => "application/octet-stream",
     }
 }
@@ -0,0 +1,234 @@
+/// How many bytes at the start of a file are needed for sniffing.
+pub const SNIFF_LENGTH: usize = 4096;
+
+/// Returns the content type of the file data, if it can be determined.
+/// Only looks at the first [SNIFF_LENGTH] bytes.
+///
+/// Servers often send the wrong content type (or just `application/octet-stream`),
+/// so this is preferred over the content type header.
+pub fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    let data = &data[..data.len().min(SNIFF_LENGTH)];
+    let starts = |sig: &[u8]| data.starts_with(sig);
+    let at = |offset: usize, sig: &[u8]| data.get(offset..offset + sig.len()) == Some(sig);
+
+    // images
+    if starts(b"\x89PNG\r\n\x1a\n") {
+        return Some(if is_apng(data) {
+            "image/apng"
+        } else {
+            "image/png"
+        });
+    }
+    if starts(b"GIF87a") || starts(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if starts(b"\xff\xd8\xff") {
+        return Some("image/jpeg");
+    }
+    if starts(b"RIFF") && at(8, b"WEBP") {
+        return Some("image/webp");
+    }
+    if starts(b"BM") && at(6, b"\0\0\0\0") {
+        return Some("image/bmp");
+    }
+    if starts(b"\0\0\x01\0") {
+        return Some("image/x-icon");
+    }
+    if starts(b"II*\0") || starts(b"MM\0*") {
+        return Some("image/tiff");
+    }
+    if starts(b"\xff\x0a") || starts(b"\0\0\0\x0cJXL \r\n\x87\n") {
+        return Some("image/jxl");
+    }
+    if at(4, b"ftyp") {
+        return Some(sniff_iso_media(data));
+    }
+
+    // audio and video
+    if starts(b"RIFF") && at(8, b"WAVE") {
+        return Some("audio/wav");
+    }
+    if starts(b"fLaC") {
+        return Some("audio/flac");
+    }
+    if starts(b"OggS") {
+        return Some(if at(28, b"\x80theora") {
+            "video/ogg"
+        } else {
+            "audio/ogg"
+        });
+    }
+    if starts(b"ID3") || is_mp3_frame(data) {
+        return Some("audio/mpeg");
+    }
+    if starts(b"\x1a\x45\xdf\xa3") {
+        // EBML: the document type says whether it's WebM or any other Matroska file
+        return Some(if contains(data, b"webm") {
+            "video/webm"
+        } else {
+            "video/x-matroska"
+        });
+    }
+
+    // fonts
+    if starts(b"wOFF") {
+        return Some("font/woff");
+    }
+    if starts(b"wOF2") {
+        return Some("font/woff2");
+    }
+    if starts(b"OTTO") {
+        return Some("font/otf");
+    }
+    if starts(b"\0\x01\0\0") {
+        return Some("font/ttf");
+    }
+
+    // documents and archives
+    if starts(b"%PDF-") {
+        return Some("application/pdf");
+    }
+    if starts(b"PK\x03\x04") || starts(b"PK\x05\x06") {
+        return Some("application/zip");
+    }
+
+    if is_svg(data) {
+        return Some("image/svg+xml");
+    }
+
+    None
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|window| window == needle)
+}
+
+/// APNGs have an acTL chunk before the first IDAT chunk.
+fn is_apng(data: &[u8]) -> bool {
+    let mut offset = 8;
+    while let Some(header) = data.get(offset..offset + 8) {
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        match &header[4..] {
+            b"acTL" => return true,
+            b"IDAT" => return false,
+            _ => (),
+        }
+        // length, type, data, CRC
+        offset += 8 + len + 4;
+    }
+    false
+}
+
+fn is_mp3_frame(data: &[u8]) -> bool {
+    // frame sync, MPEG layer III
+    data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 && data[1] & 0x06 == 0x02
+}
+
+/// Images, audio, and video in the ISO base media file format (the `ftyp` box says which).
+fn sniff_iso_media(data: &[u8]) -> &'static str {
+    let box_len = data
+        .get(..4)
+        .map_or(0, |len| {
+            u32::from_be_bytes(len.try_into().unwrap()) as usize
+        })
+        .min(data.len());
+    let major_brand = data.get(8..12).unwrap_or_default();
+    let compatible_brands = data.get(16..box_len).unwrap_or_default();
+    let has_brand = |names: &[&[u8; 4]]| {
+        std::iter::once(major_brand)
+            .chain(compatible_brands.chunks_exact(4))
+            .any(|brand| names.iter().any(|name| brand == &name[..]))
+    };
+
+    if has_brand(&[b"avif", b"avis"]) {
+        "image/avif"
+    } else if has_brand(&[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx"]) {
+        "image/heic"
+    } else if has_brand(&[b"mif1", b"msf1"]) {
+        "image/heif"
+    } else if major_brand == b"qt  " {
+        "video/quicktime"
+    } else if [&b"M4A "[..], b"M4B ", b"M4P "].contains(&major_brand) {
+        "audio/mp4"
+    } else {
+        "video/mp4"
+    }
+}
+
+fn is_svg(data: &[u8]) -> bool {
+    // the data might have been cut off in the middle of a character
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&data[..e.valid_up_to()]).unwrap(),
+        Err(_) => return false,
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    if text.starts_with("<svg") || text.starts_with("<!DOCTYPE svg") {
+        return true;
+    }
+    let is_xml = text.starts_with("<?xml") || text.starts_with("<!--");
+    is_xml && text.contains("<svg") && !text.contains("<html")
+}
+
+#[test]
+fn test_sniff_content_type() {
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR0123456789abcCRC!";
+    let mut apng = png.to_vec();
+    apng.extend_from_slice(b"\0\0\0\x08acTL01234567CRC!");
+    let mut not_apng = png.to_vec();
+    not_apng.extend_from_slice(b"\0\0\0\x04IDAT0123CRC!\0\0\0\x08acTL01234567CRC!");
+    assert_eq!(sniff_content_type(png), Some("image/png"));
+    assert_eq!(sniff_content_type(&apng), Some("image/apng"));
+    assert_eq!(sniff_content_type(&not_apng), Some("image/png"));
+
+    assert_eq!(
+        sniff_content_type(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm"),
+        Some("video/webm")
+    );
+    assert_eq!(
+        sniff_content_type(b"\x1a\x45\xdf\xa3\xa3\x42\x82\x88matroska"),
+        Some("video/x-matroska")
+    );
+
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf"),
+        Some("image/avif")
+    );
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x18ftypmif1\0\0\0\0mif1heic"),
+        Some("image/heic")
+    );
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x14ftypqt  \0\0\0\0qt  "),
+        Some("video/quicktime")
+    );
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x1cftypM4A \0\0\0\0M4A mp42isom"),
+        Some("audio/mp4")
+    );
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x18ftypisom\0\0\0\0isomavc1"),
+        Some("video/mp4")
+    );
+    // brands after the end of the ftyp box don't count
+    assert_eq!(
+        sniff_content_type(b"\0\0\0\x10ftypisom\0\0\0\0avif"),
+        Some("video/mp4")
+    );
+
+    assert_eq!(sniff_content_type(b"\xff\xfb\x90\x64"), Some("audio/mpeg"));
+    assert_eq!(sniff_content_type(b"ID3\x04\0\0"), Some("audio/mpeg"));
+    // AAC (ADTS), not layer III
+    assert_eq!(sniff_content_type(b"\xff\xf1\x50\x80"), None);
+
+    let svg = "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"><text>\u{1f431}";
+    assert_eq!(sniff_content_type(svg.as_bytes()), Some("image/svg+xml"));
+    let cut_off = &svg.as_bytes()[..svg.len() - 1];
+    assert_eq!(sniff_content_type(cut_off), Some("image/svg+xml"));
+    assert_eq!(sniff_content_type(b"<svg>\xff\xfe"), None);
+    assert_eq!(
+        sniff_content_type(b"<!-- x --><html><body><svg></svg></body></html>"),
+        None
+    );
+}
@@ -91,6 +91,12 @@ pub struct SinglePost {
     pub comments: HashMap<u64, Vec<CommentFromCohost>>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SwitchProjectInput {
+    project_id: u64,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct FollowedFeedInput {
@@ -118,7 +124,12 @@ pub struct FollowedFeedProject {
 
 impl CohostContext {
     pub async fn login_logged_in(&self) -> Result<LoginLoggedIn, GetError> {
-        self.trpc_query::<(), _>("login.loggedIn", None).await
+        let login = self.fetch_login_logged_in().await?;
+        if !login.logged_in && self.recover_if_logged_out().await? {
+            return self.fetch_login_logged_in().await;
+        }
+        self.set_logged_in(&login).await;
+        Ok(login)
     }
 
     pub async fn posts_profile_posts(
@@ -138,7 +149,16 @@ impl CohostContext {
             },
         };
 
-        self.trpc_query("posts.profilePosts", Some(input)).await
+        loop {
+            let result = self.trpc_query("posts.profilePosts", Some(&input)).await;
+            if self
+                .retry_if_logged_out(&result, |r: &ProfilePosts| &r.posts)
+                .await?
+            {
+                continue;
+            }
+            break result;
+        }
     }
 
     pub async fn posts_single_post(
@@ -153,7 +173,16 @@ impl CohostContext {
             nonce,
         };
 
-        self.trpc_query("posts.singlePost", Some(input)).await
+        loop {
+            let result = self.trpc_query("posts.singlePost", Some(&input)).await;
+            if self
+                .retry_if_logged_out(&result, |r: &SinglePost| std::slice::from_ref(&r.post))
+                .await?
+            {
+                continue;
+            }
+            break result;
+        }
     }
 
     pub async fn projects_list_edited_projects(&self) -> Result<ListEditedProjects, GetError> {
@@ -161,6 +190,14 @@ impl CohostContext {
             .await
     }
 
+    /// Changes the active page.
+    pub async fn projects_switch_project(&self, project_id: u64) -> Result<(), GetError> {
+        let input = SwitchProjectInput { project_id };
+        self.trpc_mutation::<_, serde::de::IgnoredAny>("projects.switchProject", input)
+            .await?;
+        Ok(())
+    }
+
     pub async fn projects_by_handle(&self, handle: &str) -> Result<ProjectFromCohost, GetError> {
         self.trpc_query("projects.byHandle", Some(handle)).await
     }
@@ -178,8 +215,15 @@ impl CohostContext {
             sort_order: "alpha-asc".into(),
         };
 
-        self.trpc_query("projects.followedFeed.query", Some(input))
-            .await
+        loop {
+            let result = self
+                .trpc_query("projects.followedFeed.query", Some(&input))
+                .await;
+            if self.retry_if_logged_out(&result, |_| &[]).await? {
+                continue;
+            }
+            break result;
+        }
     }
 
     pub async fn projects_followed_feed_query_all(
@@ -0,0 +1,821 @@
+use crate::bundled_files::COHOST_STATIC;
+use crate::context::CohostContext;
+use crate::data::Database;
+use crate::dl::{long_progress_style, CurrentStateV1};
+use crate::import_cdl1::import_post_page_html;
+use crate::render::rewrite::make_standalone_page;
+use crate::render::PageRenderer;
+use crate::server::content_type_for_ext;
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::read::{GzDecoder, MultiGzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::ProgressBar;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// Writes WARC/1.1 records.
+pub struct WarcWriter<W: Write> {
+    out: W,
+    gzip: bool,
+    /// Makes record IDs unique across exports
+    id_seed: String,
+    record_count: u64,
+}
+
+impl<W: Write> WarcWriter<W> {
+    /// If `gzip` is set, every record is written as its own gzip member, as is usual for .warc.gz.
+    ///
+    /// `name` should identify the output (e.g. its file path), and is used for record IDs.
+    pub fn new(out: W, gzip: bool, name: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            out,
+            gzip,
+            id_seed: format!(
+                "{name}\n{}.{}\n",
+                now.timestamp(),
+                now.timestamp_subsec_nanos()
+            ),
+            record_count: 0,
+        }
+    }
+
+    fn record_id(&mut self, seed: &str) -> String {
+        self.record_count += 1;
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.id_seed.as_bytes());
+        hasher.update(seed.as_bytes());
+        hasher.update(self.record_count.to_be_bytes());
+        let mut id = hasher.finalize();
+        // make it look like a v4 UUID
+        id[6] = (id[6] & 0x0f) | 0x40;
+        id[8] = (id[8] & 0x3f) | 0x80;
+
+        let id = hex::encode(&id[..16]);
+        format!(
+            "<urn:uuid:{}-{}-{}-{}-{}>",
+            &id[..8],
+            &id[8..12],
+            &id[12..16],
+            &id[16..20],
+            &id[20..]
+        )
+    }
+
+    fn write_record(
+        &mut self,
+        headers: &[(&str, &str)],
+        block_len: u64,
+        block: impl Read,
+    ) -> io::Result<()> {
+        let mut head = String::from("WARC/1.1\r\n");
+        for (name, value) in headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {block_len}\r\n\r\n"));
+
+        fn write(out: &mut impl Write, head: &str, mut block: impl Read) -> io::Result<()> {
+            out.write_all(head.as_bytes())?;
+            io::copy(&mut block, out)?;
+            out.write_all(b"\r\n\r\n")
+        }
+
+        if self.gzip {
+            let mut encoder = GzEncoder::new(&mut self.out, Compression::default());
+            write(&mut encoder, &head, block)?;
+            encoder.finish()?;
+        } else {
+            write(&mut self.out, &head, block)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_warcinfo(&mut self, fields: &[(&str, &str)]) -> io::Result<()> {
+        let mut block = String::new();
+        for (name, value) in fields {
+            block.push_str(&format!("{name}: {value}\r\n"));
+        }
+
+        let record_id = self.record_id("warcinfo");
+        let date = warc_date(Utc::now());
+        self.write_record(
+            &[
+                ("WARC-Type", "warcinfo"),
+                ("WARC-Record-ID", &record_id),
+                ("WARC-Date", &date),
+                ("Content-Type", "application/warc-fields"),
+            ],
+            block.len() as u64,
+            block.as_bytes(),
+        )
+    }
+
+    /// Writes a response record with a synthesized HTTP 200 response.
+    /// `body_sha256` is the hex-encoded SHA-256 of the body.
+    pub fn write_response(
+        &mut self,
+        target_uri: &str,
+        date: DateTime<Utc>,
+        content_type: &str,
+        body_sha256: &str,
+        body_len: u64,
+        body: impl Read,
+    ) -> io::Result<()> {
+        let http_head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {body_len}\r\n\r\n"
+        );
+
+        let record_id = self.record_id(target_uri);
+        let date = warc_date(date);
+        let payload_digest = format!("sha256:{body_sha256}");
+        self.write_record(
+            &[
+                ("WARC-Type", "response"),
+                ("WARC-Record-ID", &record_id),
+                ("WARC-Date", &date),
+                ("WARC-Target-URI", target_uri),
+                ("WARC-Payload-Digest", &payload_digest),
+                ("Content-Type", "application/http;msgtype=response"),
+            ],
+            http_head.len() as u64 + body_len,
+            http_head.as_bytes().chain(body),
+        )
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// A WARC record. The block is read directly from the input, and anything left unread is skipped
+/// by the next call to [`WarcReader::next_record`].
+pub struct WarcRecord<'a, R: BufRead> {
+    pub headers: Vec<(String, String)>,
+    pub block: WarcBlock<'a, R>,
+}
+
+impl<R: BufRead> WarcRecord<'_, R> {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// The block of a WARC record, limited to its Content-Length.
+pub struct WarcBlock<'a, R: BufRead> {
+    input: &'a mut R,
+    remaining: &'a mut u64,
+}
+
+impl<R: BufRead> Read for WarcBlock<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if *self.remaining == 0 {
+            return Ok(0);
+        }
+        let max = (*self.remaining).min(buf.len() as u64) as usize;
+        let len = self.input.read(&mut buf[..max])?;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        *self.remaining -= len as u64;
+        Ok(len)
+    }
+}
+
+/// Reads WARC records.
+pub struct WarcReader<R: BufRead> {
+    input: R,
+    /// Unread bytes in the block of the previous record
+    remaining: u64,
+}
+
+impl WarcReader<Box<dyn BufRead>> {
+    /// Opens a .warc or .warc.gz file.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        let gzip = path.extension().map_or(false, |ext| ext == "gz");
+
+        let input: Box<dyn BufRead> = if gzip {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(file)
+        };
+        Ok(Self::new(input))
+    }
+}
+
+impl<R: BufRead> WarcReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            remaining: 0,
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = Vec::new();
+        if self.input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()))
+    }
+
+    pub fn next_record(&mut self) -> io::Result<Option<WarcRecord<'_, R>>> {
+        let skipped = io::copy(&mut (&mut self.input).take(self.remaining), &mut io::sink())?;
+        if skipped < self.remaining {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining = 0;
+
+        let version = loop {
+            match self.read_line()? {
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+                None => return Ok(None),
+            }
+        };
+        if !version.starts_with("WARC/") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected WARC record, got {version:?}"),
+            ));
+        }
+
+        let mut headers = Vec::new();
+        loop {
+            let Some(line) = self.read_line()? else {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            };
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        self.remaining = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, len)| len.parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "missing WARC Content-Length")
+            })?;
+
+        Ok(Some(WarcRecord {
+            headers,
+            block: WarcBlock {
+                input: &mut self.input,
+                remaining: &mut self.remaining,
+            },
+        }))
+    }
+}
+
+/// Longest HTTP head that will be read from a response record
+const MAX_HTTP_HEAD_LENGTH: usize = 65536;
+
+/// An HTTP response from a WARC response record.
+pub struct HttpResponse<'a> {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Body, with transfer and content encoding removed
+    pub body: Box<dyn Read + 'a>,
+}
+
+impl<'a> HttpResponse<'a> {
+    /// Reads the status line and headers. The body is decoded as it's read.
+    pub fn read(mut data: impl BufRead + 'a) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut lines = Vec::new();
+        let mut head_len = 0;
+        loop {
+            let mut line = Vec::new();
+            if data.read_until(b'\n', &mut line)? == 0 {
+                return Err(invalid("no end of HTTP headers"));
+            }
+            head_len += line.len();
+            if head_len > MAX_HTTP_HEAD_LENGTH {
+                return Err(invalid("HTTP headers are too long"));
+            }
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+        let mut lines = lines.into_iter();
+
+        let status = lines
+            .next()
+            .and_then(|line| {
+                line.split(' ')
+                    .nth(1)
+                    .and_then(|status| status.parse().ok())
+            })
+            .ok_or_else(|| invalid("invalid HTTP status line"))?;
+
+        let headers: Vec<_> = lines
+            .filter_map(|line| {
+                line.split_once(':')
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect();
+
+        let mut response = Self {
+            status,
+            headers,
+            body: Box::new(io::empty()),
+        };
+
+        let body: Box<dyn Read + 'a> = if response
+            .header("Transfer-Encoding")
+            .map_or(false, |enc| enc.eq_ignore_ascii_case("chunked"))
+        {
+            Box::new(Dechunk::new(data))
+        } else {
+            Box::new(data)
+        };
+
+        response.body = match response
+            .header("Content-Encoding")
+            .map(|enc| enc.to_ascii_lowercase())
+            .as_deref()
+        {
+            None | Some("identity") => body,
+            Some("gzip") | Some("x-gzip") => Box::new(GzDecoder::new(body)),
+            Some("deflate") => Box::new(ZlibDecoder::new(body)),
+            Some(enc) => return Err(invalid(&format!("unsupported content encoding {enc}"))),
+        };
+
+        Ok(response)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Removes chunked transfer encoding.
+struct Dechunk<R: BufRead> {
+    input: R,
+    /// Unread bytes in the current chunk
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Dechunk<R> {
+    fn new(input: R) -> Self {
+        Self {
+            input,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    fn read_chunk_size(&mut self) -> io::Result<u64> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid chunked body");
+
+        let mut line = Vec::new();
+        self.input.read_until(b'\n', &mut line)?;
+        let size = std::str::from_utf8(&line).map_err(|_| invalid())?;
+        // ignore chunk extensions
+        let size = size.split(';').next().unwrap_or_default().trim();
+        u64::from_str_radix(size, 16).map_err(|_| invalid())
+    }
+}
+
+impl<R: BufRead> Read for Dechunk<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            self.remaining = self.read_chunk_size()?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = self.remaining.min(buf.len() as u64) as usize;
+        let len = self.input.read(&mut buf[..max])?;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= len as u64;
+
+        if self.remaining == 0 {
+            let mut line_end = [0; 2];
+            self.input.read_exact(&mut line_end)?;
+            if &line_end != b"\r\n" {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid chunked body",
+                ));
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+fn read_body(response: &mut HttpResponse) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    response.body.read_to_end(&mut body)?;
+    Ok(body)
+}
+
+fn warc_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[test]
+fn test_warc_round_trip() {
+    for gzip in [false, true] {
+        let body = b"hello world";
+        let sha256 = hex::encode(Sha256::digest(body));
+
+        let mut warc = WarcWriter::new(Vec::new(), gzip, "test.warc");
+        warc.write_warcinfo(&[("software", "test")]).unwrap();
+        for _ in 0..2 {
+            warc.write_response(
+                "https://example.com/a.txt",
+                Utc::now(),
+                "text/plain",
+                &sha256,
+                body.len() as u64,
+                body.as_slice(),
+            )
+            .unwrap();
+        }
+        let data = warc.finish().unwrap();
+
+        let input: Box<dyn BufRead> = if gzip {
+            Box::new(BufReader::new(MultiGzDecoder::new(data.as_slice())))
+        } else {
+            Box::new(data.as_slice())
+        };
+        let mut reader = WarcReader::new(input);
+
+        let mut info = reader.next_record().unwrap().unwrap();
+        assert_eq!(info.header("WARC-Type"), Some("warcinfo"));
+        let mut block = Vec::new();
+        info.block.read_to_end(&mut block).unwrap();
+        assert_eq!(block, b"software: test\r\n");
+
+        let mut record_ids = vec![info.header("WARC-Record-ID").unwrap().to_string()];
+        for i in 0..2 {
+            let record = reader.next_record().unwrap().unwrap();
+            assert_eq!(record.header("WARC-Type"), Some("response"));
+            assert_eq!(
+                record.header("warc-target-uri"),
+                Some("https://example.com/a.txt")
+            );
+            assert_eq!(
+                record.header("WARC-Payload-Digest"),
+                Some(format!("sha256:{sha256}").as_str())
+            );
+            record_ids.push(record.header("WARC-Record-ID").unwrap().to_string());
+
+            // the rest of the first record is skipped by the next call to next_record
+            let mut response = HttpResponse::read(BufReader::new(record.block)).unwrap();
+            assert_eq!(response.status, 200);
+            assert_eq!(response.header("content-type"), Some("text/plain"));
+            if i == 1 {
+                assert_eq!(read_body(&mut response).unwrap(), body);
+            }
+        }
+        assert!(reader.next_record().unwrap().is_none());
+
+        record_ids.sort();
+        record_ids.dedup();
+        assert_eq!(record_ids.len(), 3);
+    }
+
+    let mut chunked = HttpResponse::read(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+        5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n"
+            .as_slice(),
+    )
+    .unwrap();
+    assert_eq!(read_body(&mut chunked).unwrap(), b"hello, world");
+
+    let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+    gzipped.write_all(b"hello, world").unwrap();
+    let gzipped = gzipped.finish().unwrap();
+
+    let mut data = b"HTTP/1.1 404 Not Found\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+    data.extend_from_slice(&gzipped);
+    let mut response = HttpResponse::read(data.as_slice()).unwrap();
+    assert_eq!(response.status, 404);
+    assert_eq!(read_body(&mut response).unwrap(), b"hello, world");
+
+    let mut data =
+        b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    data.extend_from_slice(format!("{:x}\r\n", gzipped.len()).as_bytes());
+    data.extend_from_slice(&gzipped);
+    data.extend_from_slice(b"\r\n0\r\n\r\n");
+    let mut response = HttpResponse::read(data.as_slice()).unwrap();
+    assert_eq!(read_body(&mut response).unwrap(), b"hello, world");
+
+    let mut response = HttpResponse::read(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n".as_slice(),
+    )
+    .unwrap();
+    assert!(read_body(&mut response).is_err());
+
+    assert!(HttpResponse::read(b"HTTP/1.1 200 OK\r\n".as_slice()).is_err());
+}
+
+/// Exports all downloaded resources and rendered post pages to a WARC file.
+pub async fn export_warc(db: &Database, root_dir: &Path, out_path: &Path) -> anyhow::Result<()> {
+    let file = File::create(out_path)
+        .with_context(|| format!("could not create {}", out_path.display()))?;
+    let gzip = out_path.extension().map_or(false, |ext| ext == "gz");
+    let mut warc = WarcWriter::new(BufWriter::new(file), gzip, &out_path.display().to_string());
+
+    let software = format!("cohost-dl 2 ({})", env!("BUILD_COMMIT"));
+    warc.write_warcinfo(&[
+        ("software", &software),
+        ("format", "WARC File Format 1.1"),
+        (
+            "description",
+            "cohost.org data; post pages are re-rendered from downloaded data",
+        ),
+    ])?;
+
+    let total = db.total_url_file_count().await?;
+    let progress = ProgressBar::new(total).with_style(long_progress_style());
+    progress.set_message("resources");
+
+    let mut offset = 0;
+    loop {
+        let batch = db.get_url_files_batch(offset, EXPORT_BATCH_SIZE).await?;
+        if batch.is_empty() {
+            break;
+        }
+        offset += batch.len() as i64;
+
+        for (url, path) in batch {
+            progress.inc(1);
+
+            let resolved_path = root_dir.join(&path);
+            let (mut file, metadata) = match File::open(&resolved_path).and_then(|file| {
+                let metadata = file.metadata()?;
+                Ok((file, metadata))
+            }) {
+                Ok(file) => file,
+                Err(e) => {
+                    progress.suspend(|| warn!("skipping {url}: {e}"));
+                    continue;
+                }
+            };
+
+            let len = metadata.len();
+
+            let (content_type, validators) = match Url::parse(&url) {
+                Ok(url) => (
+                    db.get_res_content_type(&url).await?,
+                    db.get_resource_validators(&url).await?,
+                ),
+                Err(_) => (None, None),
+            };
+            let content_type = content_type.unwrap_or_else(|| {
+                let ext = path.extension().and_then(|ext| ext.to_str());
+                content_type_for_ext(ext).to_string()
+            });
+
+            let date = metadata
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now());
+
+            // the hash from the last download is usually still correct
+            let sha256 = match validators {
+                Some(validators) if validators.size as u64 == len => validators.sha256,
+                _ => {
+                    let mut hasher = Sha256::new();
+                    io::copy(&mut (&mut file).take(len), &mut hasher)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    hex::encode(hasher.finalize())
+                }
+            };
+
+            warc.write_response(&url, date, &content_type, &sha256, len, file.take(len))?;
+        }
+    }
+    progress.finish_and_clear();
+
+    let renderer = PageRenderer::new();
+    // resource URLs point to their original location, which is also in the WARC file, but cohost's
+    // static files are only kept if they were archived
+    let mut static_files = HashMap::new();
+    for line in COHOST_STATIC.lines().filter(|line| !line.is_empty()) {
+        let Ok(url) = Url::parse(line) else {
+            continue;
+        };
+        if db.get_url_file(&url).await?.is_some() {
+            static_files.insert(url.clone(), url.to_string());
+        }
+    }
+    let now = Utc::now();
+
+    let total = db.total_post_count().await?;
+    let progress = ProgressBar::new(total).with_style(long_progress_style());
+    progress.set_message("post pages");
+
+    let mut offset = 0;
+    loop {
+        let batch = db.get_post_ids(offset, EXPORT_BATCH_SIZE).await?;
+        if batch.is_empty() {
+            break;
+        }
+        offset += batch.len() as i64;
+
+        for post_id in batch {
+            progress.inc(1);
+
+            let page_url = db.post(post_id).await?.data()?.single_post_page_url;
+            // deleted posts
+            if page_url == "https://cohost.org/" {
+                continue;
+            }
+
+            let (_, handle) = db.posting_project_handle(post_id).await?;
+            let html = match renderer
+                .render_single_post(db, &handle, &post_id.to_string())
+                .await
+            {
+                Ok(html) => html,
+                Err(e) => {
+                    progress.suspend(|| warn!("skipping post {post_id}: {e}"));
+                    continue;
+                }
+            };
+
+            let html = make_standalone_page(&html, &static_files);
+
+            warc.write_response(
+                &page_url,
+                now,
+                "text/html; charset=utf-8",
+                &hex::encode(Sha256::digest(&html)),
+                html.len() as u64,
+                html.as_bytes(),
+            )?;
+        }
+    }
+    progress.finish_and_clear();
+
+    warc.finish()?;
+
+    info!("exported to {}", out_path.display());
+
+    Ok(())
+}
+
+/// Returns true if the URL looks like a post page (`/handle/post/123-slug`).
+fn is_post_page_url(url: &Url) -> bool {
+    if url.domain() != Some("cohost.org") {
+        return false;
+    }
+    let Some(segments) = url.path_segments() else {
+        return false;
+    };
+    let segments: Vec<_> = segments.collect();
+    segments.len() == 3 && segments[1] == "post" && !segments[0].is_empty()
+}
+
+/// Imports post pages and media files from a WARC file.
+pub async fn import_warc(
+    ctx: &CohostContext,
+    state: &Mutex<CurrentStateV1>,
+    path: &Path,
+    add_only: bool,
+) -> anyhow::Result<()> {
+    let mut warc =
+        WarcReader::open(path).with_context(|| format!("could not open {}", path.display()))?;
+
+    let progress = ProgressBar::new_spinner();
+    progress.enable_steady_tick(Duration::from_millis(100));
+
+    let mut record_count = 0;
+    let mut post_count = 0;
+    let mut file_count = 0;
+    let mut error_count = 0;
+
+    while let Some(record) = warc
+        .next_record()
+        .with_context(|| format!("reading {}", path.display()))?
+    {
+        record_count += 1;
+        progress.set_message(format!(
+            "{record_count} records, {post_count} posts, {file_count} files"
+        ));
+
+        if record.header("WARC-Type") != Some("response") {
+            continue;
+        }
+        let Some(url) = record
+            .header("WARC-Target-URI")
+            .map(|url| url.trim_start_matches('<').trim_end_matches('>'))
+            .and_then(|url| Url::parse(url).ok())
+        else {
+            continue;
+        };
+
+        let mut response = match HttpResponse::read(BufReader::new(record.block)) {
+            Ok(response) => response,
+            Err(e) => {
+                progress.suspend(|| debug!("skipping {url}: {e}"));
+                continue;
+            }
+        };
+        if response.status != 200 {
+            continue;
+        }
+
+        let content_type = response
+            .header("Content-Type")
+            .unwrap_or_default()
+            .to_string();
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        if essence == "text/html" && is_post_page_url(&url) {
+            let html = match read_body(&mut response) {
+                Ok(html) => String::from_utf8_lossy(&html).to_string(),
+                Err(e) => {
+                    progress.suspend(|| debug!("skipping {url}: {e}"));
+                    continue;
+                }
+            };
+            match import_post_page_html(ctx, state, html, add_only).await {
+                Ok(page) => {
+                    if page.inserted {
+                        post_count += 1;
+                    }
+                }
+                Err(e) => {
+                    progress.suspend(|| error!("error importing {url}: {e:?}\n"));
+                    error_count += 1;
+                }
+            }
+        } else if essence.starts_with("image/")
+            || essence.starts_with("audio/")
+            || essence.starts_with("video/")
+        {
+            match ctx
+                .store_resource(&url, &content_type, &mut response.body)
+                .await
+            {
+                Ok(Some(_)) => file_count += 1,
+                Ok(None) => (),
+                Err(e) => {
+                    progress.suspend(|| error!("error storing {url}: {e:?}\n"));
+                    error_count += 1;
+                }
+            }
+        }
+    }
+
+    progress.finish_and_clear();
+
+    if error_count == 0 {
+        info!(
+            "Finished importing {}: {post_count} posts, {file_count} files",
+            path.display()
+        );
+    } else {
+        info!(
+            "Finished importing {}: {post_count} posts, {file_count} files, with {error_count} errors",
+            path.display()
+        );
+    }
+
+    Ok(())
+}